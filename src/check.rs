@@ -0,0 +1,390 @@
+use twilight_http::Client;
+use twilight_model::guild::{Guild, Permissions, Role};
+use twilight_model::id::Id;
+use twilight_model::id::marker::{GuildMarker, ChannelMarker, RoleMarker, UserMarker};
+
+use std::fmt;
+use std::path::Path;
+
+use crate::config::{self, Config, Permission};
+use crate::secrets;
+use crate::welcome;
+use crate::embed;
+use crate::template::{Resolver, TemplateError};
+
+/// The result of checking the configuration against the live guild.
+pub struct Report {
+	entries: Vec<Entry>,
+}
+
+struct Entry {
+	severity: Severity,
+	message: String,
+}
+
+#[derive(PartialEq)]
+enum Severity {
+	Ok,
+	Warning,
+	Error,
+}
+
+impl Report {
+	fn new() -> Report {
+		Report {
+			entries: Vec::new(),
+		}
+	}
+
+	fn ok<M: Into<String>>(&mut self, message: M) {
+		self.push(Severity::Ok, message);
+	}
+
+	fn warning<M: Into<String>>(&mut self, message: M) {
+		self.push(Severity::Warning, message);
+	}
+
+	fn error<M: Into<String>>(&mut self, message: M) {
+		self.push(Severity::Error, message);
+	}
+
+	fn push<M: Into<String>>(&mut self, severity: Severity, message: M) {
+		self.entries.push(Entry {
+			severity,
+			message: message.into(),
+		});
+	}
+
+	pub fn has_errors(&self) -> bool {
+		self.entries.iter().any(|e| e.severity == Severity::Error)
+	}
+}
+
+impl fmt::Display for Report {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		for entry in &self.entries {
+			let label = match entry.severity {
+				Severity::Ok => "ok",
+				Severity::Warning => "warning",
+				Severity::Error => "error",
+			};
+			writeln!(f, "{:>7}  {}", label, entry.message)?;
+		}
+
+		let errors = self.entries.iter().filter(|e| e.severity == Severity::Error).count();
+		let warnings = self.entries.iter().filter(|e| e.severity == Severity::Warning).count();
+		write!(f, "\n{} errors, {} warnings", errors, warnings)
+	}
+}
+
+/// Reads the configuration and secrets and checks them against the guilds they refer to.
+pub async fn run(config_path: &Path, secrets_path: &Path) -> Report {
+	let mut report = Report::new();
+
+	let config = match std::fs::read_to_string(config_path) {
+		Ok(s) => s,
+		Err(e) => {
+			report.error(format!("Couldn't read configuration from {}: {}", config_path.display(), e));
+			return report;
+		},
+	};
+	let config: Config = match toml::from_str(&config) {
+		Ok(config) => config,
+		Err(e) => {
+			report.error(format!("Couldn't parse configuration: {}", e));
+			return report;
+		},
+	};
+	match config.validate() {
+		Ok(_) => report.ok("Configuration is valid."),
+		Err(e) => report.error(e.to_string()),
+	}
+
+	let secrets = match secrets::load(secrets_path, &config.associations()) {
+		Ok(secrets) => secrets,
+		Err(e) => {
+			report.error(format!("Couldn't read secrets: {}", e));
+			return report;
+		},
+	};
+
+	let client = Client::new(secrets.discord.token.clone());
+
+	let bot = match client.current_user().await {
+		Ok(response) => match response.model().await {
+			Ok(user) => user,
+			Err(e) => {
+				report.error(format!("Couldn't deserialize the bot user: {}", e));
+				return report;
+			},
+		},
+		Err(e) => {
+			report.error(format!("Couldn't log in with the Discord token: {}", e));
+			return report;
+		},
+	};
+	report.ok(format!("Logged in as {} ({}).", bot.name, bot.id));
+
+	for guild in config.guilds() {
+		check_guild(&client, guild, bot.id, &mut report).await;
+	}
+
+	report
+}
+
+async fn check_guild(client: &Client, config: &config::Guild, bot: Id<UserMarker>, report: &mut Report) {
+	let guild = match client.guild(config.id()).await {
+		Ok(response) => match response.model().await {
+			Ok(guild) => guild,
+			Err(e) => {
+				report.error(format!("Couldn't deserialize guild {}: {}", config.id(), e));
+				return;
+			},
+		},
+		Err(e) => {
+			report.error(format!("Couldn't find guild {}, is the bot a member of it? {}", config.id(), e));
+			return;
+		},
+	};
+	report.ok(format!("Found guild {} ({}).", guild.name, guild.id));
+
+	// Shared by the checks so that the names in the guild are only fetched once.
+	let resolver = Resolver::new(client, config);
+
+	match config.welcome() {
+		Some(welcome) => check_welcome(client, &resolver, welcome, report).await,
+		None => report.ok("No welcome message is configured."),
+	}
+
+	if let Some(greeting) = config.join().greeting() {
+		check_channel(client, guild.id, greeting.channel(), "Greeting", report).await;
+	}
+
+	for sticky in config.sticky() {
+		check_channel(client, guild.id, sticky.channel(), &format!("Sticky message {}", sticky.name()), report).await;
+	}
+
+	check_templates(&resolver, config, report).await;
+
+	// The roles that the bot gives and takes, which it has to be above.
+	let mut managed: Vec<&Role> = Vec::new();
+
+	if let Some(member) = config.member() {
+		match find_role(&guild, member.role()) {
+			Some(role) => {
+				report.ok(format!("Found member role {} ({}).", role.name, role.id));
+				managed.push(role);
+			},
+			None => report.error(format!("There is no member role {} in the guild.", member.role())),
+		}
+
+		check_permissions(client, &guild, member.permission().purge(), "purge", report).await;
+	}
+
+	let menus = config.welcome().map(|w| w.roles()).unwrap_or_default().iter()
+		.chain(config.sticky().iter().flat_map(|s| s.roles()));
+	for menu in menus {
+		for option in menu.roles() {
+			match find_role(&guild, option.role()) {
+				Some(role) => {
+					report.ok(format!("Found role {} ({}) in role menu {}.", role.name, role.id, menu.name()));
+					managed.push(role);
+				},
+				None => report.error(format!("There is no role {} from role menu {} in the guild.", option.role(), menu.name())),
+			}
+		}
+	}
+
+	if let Some(rules) = config.welcome().and_then(|w| w.rules()) {
+		match find_role(&guild, rules.role()) {
+			Some(role) => {
+				report.ok(format!("Found rules role {} ({}).", role.name, role.id));
+				managed.push(role);
+			},
+			None => report.error(format!("There is no rules role {} in the guild.", rules.role())),
+		}
+
+		check_permissions(client, &guild, rules.permission().reset(), "reset the rules", report).await;
+	}
+
+	if let Some(welcome) = config.welcome() {
+		check_permissions(client, &guild, welcome.permission().manage(), "manage the welcome message", report).await;
+	}
+
+	check_bot(client, &guild, bot, &managed, report).await;
+}
+
+async fn check_welcome(client: &Client, resolver: &Resolver<'_>, welcome: &config::Welcome, report: &mut Report) {
+	check_channel(client, resolver.guild(), welcome.channel(), "Welcome", report).await;
+
+	let embeds = match welcome.source().embeds().map(embed::from_file) {
+		Some(Ok(embeds)) => {
+			report.ok(format!("Welcome message has {} embeds.", embeds.len()));
+			embeds
+		},
+		Some(Err(e)) => {
+			report.error(format!("Couldn't read welcome message embeds: {}", e));
+			Vec::new()
+		},
+		None => Vec::new(),
+	};
+
+	let content = welcome.source().content();
+	if content.is_none() && welcome.source().embeds().is_none() {
+		report.warning("No welcome message is configured.");
+		return;
+	}
+
+	let content = rendered(resolver, content.unwrap_or_default()).await;
+	let messages = welcome::posts(&content, &embeds, Vec::new()).len();
+	if messages == 0 {
+		report.error("Welcome message is empty.");
+	} else {
+		report.ok(format!("Welcome message is {} characters, posted as {} messages.", content.chars().count(), messages));
+	}
+
+	for (locale, translation) in welcome.translations() {
+		let embeds = match translation.source().embeds().map(embed::from_file) {
+			Some(Ok(embeds)) => embeds,
+			Some(Err(e)) => {
+				report.error(format!("Couldn't read embeds of welcome translation {}: {}", locale, e));
+				continue;
+			},
+			None => Vec::new(),
+		};
+
+		let content = rendered(resolver, translation.source().content().unwrap_or_default()).await;
+		match welcome::posts(&content, &embeds, Vec::new()).len() {
+			0 => report.error(format!("Welcome translation {} is empty.", locale)),
+			messages => report.ok(format!(
+				"Welcome translation {} is {} characters, shown as {} messages.",
+				locale, content.chars().count(), messages,
+			)),
+		}
+	}
+}
+
+/// Fills in the placeholders of `content`, since mentions are longer than the names they replace
+/// and the length is checked against what is posted.
+///
+/// Placeholders that can't be filled in are left as they are, they are reported by [`check_templates`].
+async fn rendered(resolver: &Resolver<'_>, content: String) -> String {
+	resolver.render(&content).await.unwrap_or(content)
+}
+
+/// Fills in the placeholders of everything the bot posts in the guild, to find names that don't exist.
+async fn check_templates(resolver: &Resolver<'_>, config: &config::Guild, report: &mut Report) {
+	let mut templates = Vec::new();
+
+	if let Some(welcome) = config.welcome() {
+		templates.push((String::from("Welcome message"), welcome.source().content()));
+		templates.push((String::from("Welcome message embeds"), welcome.source().embeds().and_then(|f| embed::read(f).ok())));
+		for (locale, translation) in welcome.translations() {
+			templates.push((format!("Welcome translation {}", locale), translation.source().content()));
+			templates.push((format!("Embeds of welcome translation {}", locale), translation.source().embeds().and_then(|f| embed::read(f).ok())));
+		}
+	}
+	for sticky in config.sticky() {
+		templates.push((format!("Sticky message {}", sticky.name()), sticky.source().content()));
+		templates.push((format!("Embeds of sticky message {}", sticky.name()), sticky.source().embeds().and_then(|f| embed::read(f).ok())));
+	}
+	if let Some(dm) = config.join().dm() {
+		templates.push((String::from("Join direct message"), dm.content().ok()));
+	}
+	if let Some(greeting) = config.join().greeting() {
+		templates.push((String::from("Greeting"), greeting.template().content().ok()));
+	}
+
+	let mut checked = 0;
+	for (name, template) in templates {
+		// Files that can't be read are reported by the other checks.
+		let template = match template {
+			Some(template) => template,
+			None => continue,
+		};
+
+		match resolver.render(&template).await {
+			Ok(_) => checked += 1,
+			Err(e @ TemplateError::Unresolved(_)) => report.error(format!("{} has {}.", name, e)),
+			Err(e) => report.error(format!("Couldn't fill in {}: {}", name.to_lowercase(), e)),
+		}
+	}
+
+	if checked > 0 {
+		report.ok(format!("Filled in the placeholders of {} messages.", checked));
+	}
+}
+
+async fn check_channel(client: &Client, guild: Id<GuildMarker>, channel: Id<ChannelMarker>, name: &str, report: &mut Report) {
+	match client.channel(channel).await {
+		Ok(response) => match response.model().await {
+			Ok(c) if c.guild_id == Some(guild) => {
+				report.ok(format!("Found {} channel #{} ({}).", name.to_lowercase(), c.name.unwrap_or_default(), c.id));
+			},
+			Ok(_) => report.error(format!("{} channel {} isn't in the guild.", name, channel)),
+			Err(e) => report.error(format!("Couldn't deserialize {} channel {}: {}", name.to_lowercase(), channel, e)),
+		},
+		Err(e) => report.error(format!("Couldn't find {} channel {}: {}", name.to_lowercase(), channel, e)),
+	}
+}
+
+async fn check_permissions(client: &Client, guild: &Guild, permissions: &[Permission], name: &str, report: &mut Report) {
+	if permissions.is_empty() {
+		report.warning(format!("Nobody has permission to {}.", name));
+	}
+
+	for permission in permissions {
+		match permission {
+			Permission::Role(role) => match find_role(guild, *role) {
+				Some(r) => report.ok(format!("Found {} permission role {} ({}).", name, r.name, r.id)),
+				None => report.error(format!("There is no {} permission role {} in the guild.", name, role)),
+			},
+			Permission::User(user) => match client.guild_member(guild.id, *user).await {
+				Ok(_) => report.ok(format!("Found {} permission user {}.", name, user)),
+				Err(e) => report.error(format!("There is no {} permission user {} in the guild: {}", name, user, e)),
+			},
+		}
+	}
+}
+
+/// Checks that the bot can add and remove the member role.
+async fn check_bot(client: &Client, guild: &Guild, bot: Id<UserMarker>, managed: &[&Role], report: &mut Report) {
+	let member = match client.guild_member(guild.id, bot).await {
+		Ok(response) => match response.model().await {
+			Ok(member) => member,
+			Err(e) => {
+				report.error(format!("Couldn't deserialize the bot member: {}", e));
+				return;
+			},
+		},
+		Err(e) => {
+			report.error(format!("Couldn't find the bot in the guild: {}", e));
+			return;
+		},
+	};
+
+	// The @everyone role has the same id as the guild.
+	let roles: Vec<&Role> = guild.roles.iter()
+		.filter(|r| r.id.cast() == guild.id || member.roles.contains(&r.id))
+		.collect();
+
+	let permissions = roles.iter().fold(Permissions::empty(), |p, r| p | r.permissions);
+	if permissions.intersects(Permissions::MANAGE_ROLES | Permissions::ADMINISTRATOR) {
+		report.ok("The bot has the MANAGE_ROLES permission.");
+	} else {
+		report.error("The bot is missing the MANAGE_ROLES permission.");
+	}
+
+	let highest = roles.iter().map(|r| (r.position, std::cmp::Reverse(r.id))).max();
+	for role in managed {
+		if highest > Some((role.position, std::cmp::Reverse(role.id))) {
+			report.ok(format!("The bot has a role above {}.", role.name));
+		} else {
+			report.error(format!("The bot needs a role above {} to manage it.", role.name));
+		}
+	}
+}
+
+fn find_role(guild: &Guild, role: Id<RoleMarker>) -> Option<&Role> {
+	guild.roles.iter().find(|r| r.id == role)
+}
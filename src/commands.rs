@@ -0,0 +1,186 @@
+use twilight_model::application::command::{Command, CommandOption, CommandOptionType};
+use twilight_model::id::Id;
+use twilight_model::id::marker::GuildMarker;
+
+use vesper::framework::{Framework, DefaultError};
+
+use sha2::{Sha256, Digest};
+
+use tracing::{info, error};
+
+use std::sync::Arc;
+
+use crate::Context;
+
+/// Where commands are registered.
+#[derive(Clone, Copy)]
+pub enum Scope {
+	Guild(Id<GuildMarker>),
+	Global,
+}
+
+/// What [`run`] does with the commands.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CommandAction {
+	List,
+	Register,
+	Clear,
+}
+
+impl std::fmt::Display for CommandAction {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			CommandAction::List => write!(f, "list"),
+			CommandAction::Register => write!(f, "register"),
+			CommandAction::Clear => write!(f, "clear"),
+		}
+	}
+}
+
+/// The commands defined by the framework, in the form Discord expects them.
+pub fn definitions(framework: &Framework<Arc<Context>>) -> Vec<Command> {
+	let mut commands = framework.twilight_commands();
+	// The framework keeps commands and subcommands in hash maps, so sort them to get a stable order.
+	commands.sort_by(|a, b| a.name.cmp(&b.name));
+	for command in &mut commands {
+		sort_subcommands(&mut command.options);
+	}
+	crate::i18n::localize(&mut commands);
+	commands
+}
+
+fn sort_subcommands(options: &mut [CommandOption]) {
+	// The order of ordinary options is significant, so only subcommands are sorted.
+	if options.iter().all(|o| matches!(o.kind, CommandOptionType::SubCommand | CommandOptionType::SubCommandGroup)) {
+		options.sort_by(|a, b| a.name.cmp(&b.name));
+	}
+
+	for option in options {
+		if let Some(options) = &mut option.options {
+			sort_subcommands(options);
+		}
+	}
+}
+
+/// A hash of the command definitions, used to tell if they have to be registered again.
+pub fn hash(scope: Scope, commands: &[Command]) -> String {
+	let mut hasher = Sha256::new();
+	match scope {
+		Scope::Guild(guild) => hasher.update(guild.to_string()),
+		Scope::Global => hasher.update("global"),
+	}
+	// Going through a JSON value sorts the keys, which the hash maps of localizations don't keep in order.
+	let commands = serde_json::to_value(commands).expect("Commands can be serialized.");
+	hasher.update(serde_json::to_vec(&commands).expect("Commands can be serialized."));
+
+	hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Replaces the registered commands with the ones defined by the framework.
+/// Commands that are no longer defined are removed.
+pub async fn register(framework: &Framework<Arc<Context>>, scope: Scope) -> Result<Vec<Command>, DefaultError> {
+	set(framework, scope, &definitions(framework)).await
+}
+
+/// Removes all registered commands.
+pub async fn clear(framework: &Framework<Arc<Context>>, scope: Scope) -> Result<(), DefaultError> {
+	set(framework, scope, &[]).await.map(|_| ())
+}
+
+pub async fn list(framework: &Framework<Arc<Context>>, scope: Scope) -> Result<Vec<Command>, DefaultError> {
+	let client = framework.interaction_client();
+	let response = match scope {
+		Scope::Guild(guild) => client.guild_commands(guild).await?,
+		Scope::Global => client.global_commands().await?,
+	};
+
+	Ok(response.models().await?)
+}
+
+async fn set(framework: &Framework<Arc<Context>>, scope: Scope, commands: &[Command]) -> Result<Vec<Command>, DefaultError> {
+	let client = framework.interaction_client();
+	let response = match scope {
+		Scope::Guild(guild) => client.set_guild_commands(guild, commands).await?,
+		Scope::Global => client.set_global_commands(commands).await?,
+	};
+
+	Ok(response.models().await?)
+}
+
+/// Registers the commands in the guild, unless the same definitions were registered last time.
+pub async fn sync(framework: &Framework<Arc<Context>>, guild: Id<GuildMarker>) -> Result<(), DefaultError> {
+	let state = &framework.data.state;
+	let commands = definitions(framework);
+	let hash = hash(Scope::Guild(guild), &commands);
+
+	match state.commands_hash(guild) {
+		Ok(Some(registered)) if registered == hash => {
+			info!(%guild, "Commands are unchanged, skipping registration.");
+			return Ok(());
+		},
+		Ok(_) => (),
+		Err(e) => error!(error = %e, "Couldn't read registered commands from state!"),
+	}
+
+	set(framework, Scope::Guild(guild), &commands).await?;
+	info!(%guild, count = commands.len(), "Registered guild commands.");
+
+	if let Err(e) = state.set_commands_hash(guild, Some(hash)) {
+		error!(error = %e, "Couldn't write state!");
+	}
+
+	Ok(())
+}
+
+/// Renders a command and its subcommands as indented lines.
+pub fn describe(command: &Command) -> String {
+	let mut s = format!("/{}", command.name);
+	if let Some(id) = command.id {
+		s.push_str(&format!(" ({})", id));
+	}
+	s.push_str(&format!(": {}", command.description));
+
+	for option in &command.options {
+		if matches!(option.kind, CommandOptionType::SubCommand | CommandOptionType::SubCommandGroup) {
+			s.push_str(&format!("\n  {} {}: {}", command.name, option.name, option.description));
+		}
+	}
+
+	s
+}
+
+/// Runs one of the `commands` subcommands and prints the result.
+pub async fn run(framework: &Framework<Arc<Context>>, action: CommandAction, scope: Scope) -> Result<(), DefaultError> {
+	match action {
+		CommandAction::List => {
+			let commands = list(framework, scope).await?;
+			if commands.is_empty() {
+				println!("No commands are registered.");
+			}
+			for command in &commands {
+				println!("{}", describe(command));
+			}
+		},
+		CommandAction::Register => {
+			let commands = register(framework, scope).await?;
+			if let Scope::Guild(guild) = scope {
+				let hash = hash(scope, &definitions(framework));
+				if let Err(e) = framework.data.state.set_commands_hash(guild, Some(hash)) {
+					error!(error = %e, "Couldn't write state!");
+				}
+			}
+			println!("Registered {} commands.", commands.len());
+		},
+		CommandAction::Clear => {
+			clear(framework, scope).await?;
+			if let Scope::Guild(guild) = scope {
+				if let Err(e) = framework.data.state.set_commands_hash(guild, None) {
+					error!(error = %e, "Couldn't write state!");
+				}
+			}
+			println!("Removed all commands.");
+		},
+	}
+
+	Ok(())
+}
@@ -3,26 +3,203 @@ use twilight_model::id::marker::{GuildMarker, ChannelMarker, UserMarker, RoleMar
 
 use serde::{Serialize, Deserialize};
 
+use std::collections::{BTreeMap, HashSet};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
 pub const DEFAULT_PATH: &str = "config.toml";
 
-#[derive(Deserialize, Serialize, Clone)]
+#[derive(Deserialize, Clone)]
+#[serde(try_from = "RawConfig")]
 pub struct Config {
-	guild: Id<GuildMarker>,
-	welcome: Welcome,
-	ebas: Ebas,
-	member: Member,
+	guilds: Vec<Arc<Guild>>,
+	log: Log,
+	http: Option<Http>,
+	shutdown: Shutdown,
+	gateway: Gateway,
+	state: State,
+	locale: Locale,
+	modules: Modules,
+}
+
+/// The configuration as it's written, which either has a list of guilds or,
+/// like before the bot supported several guilds, the settings of a single guild
+/// at the top level.
+#[derive(Deserialize)]
+struct RawConfig {
+	#[serde(default)]
+	guilds: Vec<Guild>,
+	guild: Option<Id<GuildMarker>>,
+	welcome: Option<Welcome>,
+	ebas: Option<Ebas>,
+	member: Option<Member>,
+	join: Option<Join>,
+	sticky: Option<Vec<Sticky>>,
+	#[serde(default)]
+	log: Log,
+	http: Option<Http>,
+	#[serde(default)]
+	shutdown: Shutdown,
+	#[serde(default)]
+	gateway: Gateway,
+	#[serde(default)]
+	state: State,
+	#[serde(default)]
+	locale: Locale,
+	#[serde(default)]
+	modules: Modules,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Guild {
+	id: Id<GuildMarker>,
+	welcome: Option<Welcome>,
+	// Only needed by the member module.
+	ebas: Option<Ebas>,
+	member: Option<Member>,
+	// What the bot does when someone joins the guild.
+	#[serde(default)]
+	join: Join,
+	// Other messages that the bot keeps in sync with a file, like the welcome message.
+	#[serde(default)]
+	sticky: Vec<Sticky>,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+	Io(std::io::Error),
+	Parse(toml::de::Error),
+	Invalid(String),
 }
 
 #[derive(Deserialize, Serialize, Clone)]
 pub struct Welcome {
 	channel: Id<ChannelMarker>,
+	#[serde(flatten)]
+	source: MessageSource,
+	// Menus that members use to pick roles, attached to the last message.
+	#[serde(default)]
+	roles: Vec<RoleMenu>,
+	// A button for accepting the rules, attached to the last message after the role menus.
+	rules: Option<Rules>,
+	// The welcome message in other languages, keyed by Discord locale such as "en-US".
+	// Each gets a button that shows it to the member who clicks it.
+	#[serde(default)]
+	translations: BTreeMap<String, Translation>,
+	#[serde(default)]
+	permission: WelcomePermission,
+}
+
+#[derive(Deserialize, Serialize, Clone, Default)]
+pub struct WelcomePermission {
+	// Who may preview, sync and repost the welcome message with /welcome.
+	#[serde(default)]
+	manage: Vec<Permission>,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Sticky {
+	// Identifies the message in the state, so it shouldn't be changed once the message is posted.
+	name: String,
+	channel: Id<ChannelMarker>,
+	#[serde(flatten)]
+	source: MessageSource,
+	// Role menus attached to the last message, named uniquely together with those of the welcome message.
+	#[serde(default)]
+	roles: Vec<RoleMenu>,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Translation {
+	// The text of the button, preferably in the language itself.
+	label: String,
+	#[serde(flatten)]
+	source: MessageSource,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Rules {
+	// The role given to members who accept the rules.
+	role: Id<RoleMarker>,
+	label: Option<String>,
+	#[serde(default)]
+	permission: RulesPermission,
+}
+
+#[derive(Deserialize, Serialize, Clone, Default)]
+pub struct RulesPermission {
+	#[serde(default)]
+	reset: Vec<Permission>,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct RoleMenu {
+	// Identifies the menu in interactions, so it must be unique and shouldn't be changed.
+	name: String,
+	#[serde(default)]
+	style: RoleMenuStyle,
+	// The text shown in a select menu before anything is chosen.
+	placeholder: Option<String>,
+	// The number of roles a member can have from the menu at once, all of them if not set.
+	max: Option<u8>,
+	roles: Vec<RoleOption>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RoleMenuStyle {
+	#[default]
+	Select,
+	Buttons,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct RoleOption {
+	role: Id<RoleMarker>,
+	label: String,
+	description: Option<String>,
+	// A unicode emoji or a custom emoji such as <:ac:519296526644936705>.
+	emoji: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Default)]
+pub struct Join {
+	// A direct message to the new member.
+	dm: Option<Template>,
+	// A post in a channel of the guild.
+	greeting: Option<Greeting>,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Greeting {
+	channel: Id<ChannelMarker>,
+	#[serde(flatten)]
+	template: Template,
+}
+
+/// A message with placeholders such as {user}, given inline or in a file.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Template {
+	#[serde(flatten)]
+	source: MessageSource,
+}
+
+/// Where the content of a message is read from. An inline text overrides the file.
+#[derive(Deserialize, Serialize, Clone, Default)]
+pub struct MessageSource {
 	file: Option<String>,
 	text: Option<String>,
+	// A TOML or JSON file with embeds that are posted after the text.
+	embeds: Option<String>,
 }
 
 #[derive(Deserialize, Serialize, Clone)]
 pub struct Ebas {
 	url: String,
+	// The name of the credentials in the secrets, if not the default ones.
+	association: Option<String>,
 }
 
 #[derive(Deserialize, Serialize, Clone)]
@@ -36,6 +213,71 @@ pub struct MemberPermission {
 	purge: Vec<Permission>,
 }
 
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Log {
+	#[serde(default = "Log::default_level")]
+	level: String,
+	#[serde(default)]
+	format: LogFormat,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+	#[default]
+	Text,
+	Json,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Http {
+	address: SocketAddr,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Shutdown {
+	// Seconds to wait for running commands when shutting down.
+	#[serde(default = "Shutdown::default_timeout")]
+	timeout: u64,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Gateway {
+	// The number of shards, which Discord requires to be more than one in 2500 guilds or more.
+	#[serde(default = "Gateway::default_shards")]
+	shards: u64,
+	// Seconds to wait before reconnecting after an error, doubled for every error in a row.
+	#[serde(default = "Gateway::default_backoff")]
+	backoff: u64,
+	#[serde(default = "Gateway::default_max_backoff")]
+	max_backoff: u64,
+}
+
+#[derive(Deserialize, Serialize, Clone, Default)]
+pub struct State {
+	backend: Option<StateBackend>,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Locale {
+	// The language of replies to users whose locale has no catalog, see the locales directory.
+	#[serde(default = "Locale::default_fallback")]
+	fallback: String,
+}
+
+/// Which modules of the bot are enabled, keyed by the name of the module. Modules that
+/// aren't listed are enabled.
+#[derive(Deserialize, Serialize, Clone, Default)]
+#[serde(transparent)]
+pub struct Modules(BTreeMap<String, bool>);
+
+#[derive(Deserialize, Serialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum StateBackend {
+	Toml,
+	Sqlite,
+}
+
 #[derive(Deserialize, Serialize, Clone)]
 #[serde(rename_all = "snake_case")]
 pub enum Permission {
@@ -43,44 +285,526 @@ pub enum Permission {
 	Role(Id<RoleMarker>),
 }
 
+impl From<std::io::Error> for ConfigError {
+	fn from(error: std::io::Error) -> ConfigError {
+		ConfigError::Io(error)
+	}
+}
+
+impl From<toml::de::Error> for ConfigError {
+	fn from(error: toml::de::Error) -> ConfigError {
+		ConfigError::Parse(error)
+	}
+}
+
+impl std::fmt::Display for ConfigError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			ConfigError::Io(e) => write!(f, "couldn't read configuration: {}", e),
+			ConfigError::Parse(e) => write!(f, "couldn't parse configuration: {}", e),
+			ConfigError::Invalid(reason) => write!(f, "invalid configuration: {}", reason),
+		}
+	}
+}
+
+impl std::error::Error for ConfigError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			ConfigError::Io(e) => Some(e),
+			ConfigError::Parse(e) => Some(e),
+			ConfigError::Invalid(_) => None,
+		}
+	}
+}
+
+/// Reads and validates the configuration.
+pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Config, ConfigError> {
+	let s = std::fs::read_to_string(path)?;
+	from_str(&s)
+}
+
+/// Parses and validates a configuration that isn't read from a file, e.g. in tests.
+pub fn from_str(s: &str) -> Result<Config, ConfigError> {
+	let config: Config = toml::from_str(s)?;
+	config.validate()?;
+	Ok(config)
+}
+
+impl TryFrom<RawConfig> for Config {
+	type Error = String;
+
+	fn try_from(raw: RawConfig) -> Result<Config, String> {
+		let guilds = match raw.guild {
+			Some(id) => {
+				if !raw.guilds.is_empty() {
+					return Err(String::from("use either guild or guilds, not both"));
+				}
+
+				vec![Guild {
+					id,
+					welcome: raw.welcome,
+					ebas: raw.ebas,
+					member: raw.member,
+					join: raw.join.unwrap_or_default(),
+					sticky: raw.sticky.unwrap_or_default(),
+				}]
+			},
+			None => {
+				if raw.welcome.is_some() || raw.ebas.is_some() || raw.member.is_some() || raw.join.is_some() || raw.sticky.is_some() {
+					return Err(String::from("welcome, ebas, member, join and sticky have to be set per guild when using guilds"));
+				}
+
+				raw.guilds
+			},
+		};
+
+		Ok(Config {
+			guilds: guilds.into_iter().map(Arc::new).collect(),
+			log: raw.log,
+			http: raw.http,
+			shutdown: raw.shutdown,
+			gateway: raw.gateway,
+			state: raw.state,
+			locale: raw.locale,
+			modules: raw.modules,
+		})
+	}
+}
+
 impl Config {
-	pub fn guild(&self) -> Id<GuildMarker> {
-		self.guild
+	/// Checks the parts of the configuration that can't be expressed in its types.
+	pub fn validate(&self) -> Result<(), ConfigError> {
+		if self.guilds.is_empty() {
+			return Err(ConfigError::Invalid(String::from("no guilds are configured")));
+		}
+
+		let mut ids = HashSet::new();
+		for guild in &self.guilds {
+			if !ids.insert(guild.id) {
+				return Err(ConfigError::Invalid(format!("guild {} is configured more than once", guild.id)));
+			}
+
+			guild.validate()
+				.map_err(|reason| ConfigError::Invalid(format!("guild {}: {}", guild.id, reason)))?;
+
+			for module in crate::module::enabled(self) {
+				module.validate(guild)
+					.map_err(|reason| ConfigError::Invalid(format!("guild {}: {}", guild.id, reason)))?;
+			}
+		}
+
+		if let Some(name) = self.modules.0.keys().find(|name| !crate::module::NAMES.contains(&name.as_str())) {
+			return Err(ConfigError::Invalid(format!("there is no module {}", name)));
+		}
+
+		if self.gateway.shards == 0 {
+			return Err(ConfigError::Invalid(String::from("gateway.shards has to be at least 1")));
+		}
+
+		if !crate::i18n::is_supported(&self.locale.fallback) {
+			return Err(ConfigError::Invalid(format!("there are no messages in the fallback language {}", self.locale.fallback)));
+		}
+
+		Ok(())
+	}
+
+	pub fn guilds(&self) -> &[Arc<Guild>] {
+		&self.guilds
+	}
+
+	pub fn guild(&self, id: Id<GuildMarker>) -> Option<Arc<Guild>> {
+		self.guilds.iter().find(|g| g.id == id).cloned()
+	}
+
+	/// The eBas associations whose credentials are needed, where `None` is the default one.
+	/// Only the guilds with `[guilds.ebas]` use eBas, and only if the member module is enabled.
+	pub fn associations(&self) -> Vec<Option<String>> {
+		if !crate::module::enabled(self).any(|m| m.name() == "member") {
+			return Vec::new();
+		}
+
+		let mut associations: Vec<Option<String>> = self.guilds.iter()
+			.filter_map(|g| Some(g.ebas.as_ref()?.association.clone()))
+			.collect();
+		associations.sort();
+		associations.dedup();
+		associations
+	}
+
+	pub fn log(&self) -> &Log {
+		&self.log
+	}
+
+	pub fn http(&self) -> Option<&Http> {
+		self.http.as_ref()
 	}
 
-	pub fn welcome(&self) -> &Welcome {
-		&self.welcome
+	pub fn shutdown(&self) -> &Shutdown {
+		&self.shutdown
 	}
 
-	pub fn ebas(&self) -> &Ebas {
-		&self.ebas
+	pub fn gateway(&self) -> &Gateway {
+		&self.gateway
 	}
 
-	pub fn member(&self) -> &Member {
-		&self.member
+	pub fn state(&self) -> &State {
+		&self.state
+	}
+
+	pub fn locale(&self) -> &Locale {
+		&self.locale
+	}
+
+	pub fn modules(&self) -> &Modules {
+		&self.modules
 	}
 }
 
-impl Welcome {
+impl Guild {
+	fn validate(&self) -> Result<(), String> {
+		let mut menus = HashSet::new();
+
+		if let Some(welcome) = &self.welcome {
+			welcome.source.validate("welcome")?;
+
+			for (locale, translation) in &welcome.translations {
+				if !crate::i18n::is_discord_locale(locale) {
+					return Err(format!("welcome translation {} isn't a Discord locale such as en-US", locale));
+				}
+				if translation.source.is_empty() {
+					return Err(format!("welcome translation {} has no text or embeds", locale));
+				}
+				translation.source.validate(&format!("welcome translation {}", locale))?;
+			}
+
+			welcome.validate_components(&mut menus)?;
+		}
+
+		let mut stickies = HashSet::new();
+		for sticky in &self.sticky {
+			if !stickies.insert(&sticky.name) {
+				return Err(format!("sticky message {} is configured more than once", sticky.name));
+			}
+			if sticky.source.is_empty() {
+				return Err(format!("sticky message {} has no text or embeds", sticky.name));
+			}
+			sticky.source.validate(&format!("sticky message {}", sticky.name))?;
+			if validate_menus(&sticky.roles, &mut menus)? > 5 {
+				return Err(format!("the role menus of sticky message {} need more than the five rows Discord allows", sticky.name));
+			}
+		}
+
+		let templates = self.join.dm.iter().chain(self.join.greeting.as_ref().map(|g| &g.template));
+		for template in templates {
+			if template.source.read().is_none() {
+				return Err(String::from("join messages need a text or a file"));
+			}
+			// Join messages are only sent as text.
+			if template.source.embeds.is_some() {
+				return Err(String::from("join messages can't have embeds"));
+			}
+			template.source.validate("join message")?;
+		}
+
+		if let Some(ebas) = &self.ebas {
+			reqwest::Url::parse(&ebas.url)
+				.map_err(|e| format!("eBas URL {}: {}", ebas.url, e))?;
+		}
+
+		Ok(())
+	}
+
+	pub fn id(&self) -> Id<GuildMarker> {
+		self.id
+	}
+
+	pub fn welcome(&self) -> Option<&Welcome> {
+		self.welcome.as_ref()
+	}
+
+	pub fn ebas(&self) -> Option<&Ebas> {
+		self.ebas.as_ref()
+	}
+
+	pub fn member(&self) -> Option<&Member> {
+		self.member.as_ref()
+	}
+
+	pub fn join(&self) -> &Join {
+		&self.join
+	}
+
+	pub fn sticky(&self) -> &[Sticky] {
+		&self.sticky
+	}
+
+	/// Looks up a role menu by name, in the welcome message or any of the sticky messages.
+	pub fn role_menu(&self, name: &str) -> Option<&RoleMenu> {
+		let welcome = self.welcome.iter().flat_map(|w| &w.roles);
+		let sticky = self.sticky.iter().flat_map(|s| &s.roles);
+		welcome.chain(sticky).find(|m| m.name == name)
+	}
+}
+
+impl Join {
+	pub fn dm(&self) -> Option<&Template> {
+		self.dm.as_ref()
+	}
+
+	pub fn greeting(&self) -> Option<&Greeting> {
+		self.greeting.as_ref()
+	}
+}
+
+impl Greeting {
 	pub fn channel(&self) -> Id<ChannelMarker> {
 		self.channel
 	}
 
+	pub fn template(&self) -> &Template {
+		&self.template
+	}
+}
+
+impl Template {
+	pub fn source(&self) -> &MessageSource {
+		&self.source
+	}
+
+	pub fn content(&self) -> std::io::Result<String> {
+		// Validation makes sure that either the text or the file is set.
+		self.source.read().unwrap_or_else(|| Ok(String::new()))
+	}
+}
+
+impl MessageSource {
+	/// The file the content is read from, unless it's overridden by an inline text.
+	pub fn file(&self) -> Option<PathBuf> {
+		match (&self.text, &self.file) {
+			(None, Some(f)) => Some(PathBuf::from(f)),
+			_ => None,
+		}
+	}
+
+	pub fn embeds(&self) -> Option<PathBuf> {
+		self.embeds.as_ref().map(PathBuf::from)
+	}
+
+	/// Reads the content, or returns `None` if neither a text nor a file is given.
+	pub fn read(&self) -> Option<std::io::Result<String>> {
+		match (&self.text, &self.file) {
+			(Some(t), _) => Some(Ok(t.clone())),
+			(None, Some(f)) => Some(std::fs::read_to_string(f)),
+			(None, None) => None,
+		}
+	}
+
+	/// The content, or `None` if it isn't given or the file can't be read.
 	pub fn content(&self) -> Option<String> {
-		// Start by checking the text key, i.e. it will override the file.
-		if let Some(t) = &self.text {
-			return Some(t.clone());
+		self.read()?.ok()
+	}
+
+	/// Whether there is neither a text, a file nor embeds to post.
+	pub fn is_empty(&self) -> bool {
+		self.text.is_none() && self.file.is_none() && self.embeds.is_none()
+	}
+
+	/// Checks that the files exist and that the embeds can be read, naming the message as `name`.
+	fn validate(&self, name: &str) -> Result<(), String> {
+		if let Some(f) = self.file() {
+			std::fs::metadata(&f).map_err(|e| format!("{} file {}: {}", name, f.display(), e))?;
+		}
+		if let Some(f) = self.embeds() {
+			crate::embed::from_file(f).map_err(|e| format!("{} embeds: {}", name, e))?;
 		}
+		Ok(())
+	}
+}
 
-		if let Some(f) = &self.file {
-			// TODO Throwing away info here by turning it into an Option.
-			let content = std::fs::read_to_string(f).ok();
-			return content;
+/// Checks the role menus of a message and returns the number of rows they take.
+///
+/// Menus are found by name when they are used, so `names` collects the names of the
+/// menus of every message in the guild.
+fn validate_menus(menus: &[RoleMenu], names: &mut HashSet<String>) -> Result<usize, String> {
+	let mut rows = 0;
+
+	for menu in menus {
+		if !names.insert(menu.name.clone()) {
+			return Err(format!("role menu {} is configured more than once", menu.name));
+		}
+		if menu.name.contains(':') {
+			return Err(format!("role menu {} can't contain a colon in its name", menu.name));
+		}
+		if menu.roles.is_empty() {
+			return Err(format!("role menu {} has no roles", menu.name));
 		}
+		if menu.max == Some(0) {
+			return Err(format!("role menu {} has a max of 0", menu.name));
+		}
+
+		let mut roles = HashSet::new();
+		if let Some(option) = menu.roles.iter().find(|o| !roles.insert(o.role)) {
+			return Err(format!("role {} is in role menu {} more than once", option.role, menu.name));
+		}
+
+		// Discord allows 25 options in a select menu, five buttons per row and five rows per message.
+		rows += match menu.style {
+			RoleMenuStyle::Select if menu.roles.len() > 25 => {
+				return Err(format!("role menu {} has more than 25 roles", menu.name));
+			},
+			RoleMenuStyle::Select => 1,
+			RoleMenuStyle::Buttons => menu.roles.len().div_ceil(5),
+		};
+	}
+
+	Ok(rows)
+}
+
+impl Welcome {
+	pub fn channel(&self) -> Id<ChannelMarker> {
+		self.channel
+	}
+
+	pub fn source(&self) -> &MessageSource {
+		&self.source
+	}
+
+	pub fn roles(&self) -> &[RoleMenu] {
+		&self.roles
+	}
+
+	pub fn rules(&self) -> Option<&Rules> {
+		self.rules.as_ref()
+	}
+
+	pub fn translations(&self) -> &BTreeMap<String, Translation> {
+		&self.translations
+	}
+
+	pub fn permission(&self) -> &WelcomePermission {
+		&self.permission
+	}
+
+	fn validate_components(&self, names: &mut HashSet<String>) -> Result<(), String> {
+		// The components are attached to the welcome message, so there has to be one.
+		let components = !self.roles.is_empty() || self.rules.is_some() || !self.translations.is_empty();
+		if components && self.source.is_empty() {
+			return Err(String::from("role menus, the rules button and translations need a welcome text or embeds to be attached to"));
+		}
+
+		// The buttons for the translations share a row.
+		if self.translations.len() > 5 {
+			return Err(String::from("there can be at most five welcome translations"));
+		}
+
+		// The rules button and the translation buttons take a row each.
+		let rows = usize::from(self.rules.is_some()) + usize::from(!self.translations.is_empty());
+
+		if rows + validate_menus(&self.roles, names)? > 5 {
+			return Err(String::from("the role menus need more than the five rows Discord allows"));
+		}
+
+		Ok(())
+	}
+
+}
+
+impl Translation {
+	pub fn label(&self) -> &str {
+		&self.label
+	}
+
+	pub fn source(&self) -> &MessageSource {
+		&self.source
+	}
+}
+
+impl Sticky {
+	pub fn name(&self) -> &str {
+		&self.name
+	}
+
+	pub fn channel(&self) -> Id<ChannelMarker> {
+		self.channel
+	}
+
+	pub fn source(&self) -> &MessageSource {
+		&self.source
+	}
+
+	pub fn roles(&self) -> &[RoleMenu] {
+		&self.roles
+	}
+}
+
+impl WelcomePermission {
+	pub fn manage(&self) -> &Vec<Permission> {
+		&self.manage
+	}
+}
+
+impl Rules {
+	pub fn role(&self) -> Id<RoleMarker> {
+		self.role
+	}
+
+	pub fn label(&self) -> &str {
+		self.label.as_deref().unwrap_or("I accept the rules")
+	}
+
+	pub fn permission(&self) -> &RulesPermission {
+		&self.permission
+	}
+}
 
-		// Neither a file path nor a text was given in the configuration,
-		// so we have no welcome message.
-		None
+impl RulesPermission {
+	pub fn reset(&self) -> &Vec<Permission> {
+		&self.reset
+	}
+}
+
+impl RoleMenu {
+	pub fn name(&self) -> &str {
+		&self.name
+	}
+
+	pub fn style(&self) -> RoleMenuStyle {
+		self.style
+	}
+
+	pub fn placeholder(&self) -> Option<&str> {
+		self.placeholder.as_deref()
+	}
+
+	/// The number of roles a member can have from the menu at once.
+	pub fn max(&self) -> usize {
+		self.max.map_or(self.roles.len(), usize::from).min(self.roles.len())
+	}
+
+	pub fn roles(&self) -> &[RoleOption] {
+		&self.roles
+	}
+
+	pub fn role(&self, role: Id<RoleMarker>) -> Option<&RoleOption> {
+		self.roles.iter().find(|o| o.role == role)
+	}
+}
+
+impl RoleOption {
+	pub fn role(&self) -> Id<RoleMarker> {
+		self.role
+	}
+
+	pub fn label(&self) -> &str {
+		&self.label
+	}
+
+	pub fn description(&self) -> Option<&str> {
+		self.description.as_deref()
+	}
+
+	pub fn emoji(&self) -> Option<&str> {
+		self.emoji.as_deref()
 	}
 }
 
@@ -88,6 +812,10 @@ impl Ebas {
 	pub fn url(&self) -> &String {
 		&self.url
 	}
+
+	pub fn association(&self) -> Option<&str> {
+		self.association.as_deref()
+	}
 }
 
 impl Member {
@@ -104,4 +832,115 @@ impl MemberPermission {
 	pub fn purge(&self) -> &Vec<Permission> {
 		&self.purge
 	}
-}
\ No newline at end of file
+}
+
+impl Log {
+	fn default_level() -> String {
+		String::from("info")
+	}
+
+	pub fn level(&self) -> &str {
+		&self.level
+	}
+
+	pub fn format(&self) -> LogFormat {
+		self.format
+	}
+}
+
+impl Default for Log {
+	fn default() -> Log {
+		Log {
+			level: Log::default_level(),
+			format: LogFormat::default(),
+		}
+	}
+}
+
+impl Http {
+	pub fn address(&self) -> SocketAddr {
+		self.address
+	}
+}
+
+impl Default for Gateway {
+	fn default() -> Gateway {
+		Gateway {
+			shards: Gateway::default_shards(),
+			backoff: Gateway::default_backoff(),
+			max_backoff: Gateway::default_max_backoff(),
+		}
+	}
+}
+
+impl Gateway {
+	fn default_shards() -> u64 {
+		1
+	}
+
+	fn default_backoff() -> u64 {
+		1
+	}
+
+	fn default_max_backoff() -> u64 {
+		120
+	}
+
+	pub fn shards(&self) -> u64 {
+		self.shards
+	}
+
+	/// How long to wait before reconnecting after `errors` errors in a row.
+	pub fn backoff(&self, errors: u32) -> Duration {
+		let seconds = self.backoff.saturating_mul(2u64.saturating_pow(errors.saturating_sub(1)));
+		Duration::from_secs(seconds.min(self.max_backoff))
+	}
+}
+
+impl Shutdown {
+	fn default_timeout() -> u64 {
+		30
+	}
+
+	pub fn timeout(&self) -> Duration {
+		Duration::from_secs(self.timeout)
+	}
+}
+
+impl Default for Shutdown {
+	fn default() -> Shutdown {
+		Shutdown {
+			timeout: Shutdown::default_timeout(),
+		}
+	}
+}
+
+impl Locale {
+	fn default_fallback() -> String {
+		String::from("en")
+	}
+
+	pub fn fallback(&self) -> &str {
+		&self.fallback
+	}
+}
+
+impl Default for Locale {
+	fn default() -> Locale {
+		Locale {
+			fallback: Locale::default_fallback(),
+		}
+	}
+}
+
+impl State {
+	pub fn backend(&self) -> Option<StateBackend> {
+		self.backend
+	}
+}
+
+impl Modules {
+	pub fn enabled(&self, name: &str) -> bool {
+		self.0.get(name).copied().unwrap_or(true)
+	}
+}
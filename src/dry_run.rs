@@ -0,0 +1,184 @@
+use axum::Router;
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::{header, HeaderMap, Method, StatusCode, Uri};
+use axum::response::{IntoResponse, Response};
+
+use twilight_model::id::Id;
+use twilight_model::id::marker::ApplicationMarker;
+
+use serde_json::{json, Value};
+
+use tracing::{info, error};
+
+use std::net::{SocketAddr, TcpListener};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Where the requests that go through are sent when running for real.
+pub const DISCORD: &str = "https://discord.com";
+
+struct Proxy {
+	client: reqwest::Client,
+	upstream: String,
+	application: Id<ApplicationMarker>,
+	// Ids for the messages that were never created.
+	next_id: AtomicU64,
+}
+
+/// Starts a local stand-in for the Discord API, for a client that uses it as its proxy.
+///
+/// Reads and replies to interactions are sent on to `upstream`. Other requests, which would
+/// change something in the guild, are logged with their payload and answered as if they
+/// were carried out. Must be called from within a Tokio runtime.
+pub fn start(application: Id<ApplicationMarker>, upstream: &str) -> std::io::Result<SocketAddr> {
+	let listener = TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0)))?;
+	listener.set_nonblocking(true)?;
+	let address = listener.local_addr()?;
+
+	let proxy = Arc::new(Proxy {
+		client: reqwest::Client::new(),
+		upstream: String::from(upstream),
+		application,
+		next_id: AtomicU64::new(1),
+	});
+	let app = Router::new()
+		.fallback(handle)
+		.with_state(proxy);
+
+	let server = axum::Server::from_tcp(listener)
+		.map_err(std::io::Error::other)?
+		.serve(app.into_make_service());
+	tokio::spawn(async move {
+		if let Err(e) = server.await {
+			error!(error = %e, "Dry run proxy stopped!");
+		}
+	});
+
+	Ok(address)
+}
+
+async fn handle(State(proxy): State<Arc<Proxy>>, method: Method, uri: Uri, headers: HeaderMap, body: Bytes) -> Response {
+	let path = uri.path();
+	let route = path.strip_prefix("/api/v10").unwrap_or(path);
+
+	if proxy.passes(&method, route) {
+		return proxy.forward(method, &uri, headers, body).await;
+	}
+
+	let payload = payload(&headers, &body);
+	info!(%method, path = route, payload, "Dry run, not sending request.");
+	proxy.pretend(&method, route, &payload)
+}
+
+impl Proxy {
+	fn passes(&self, method: &Method, route: &str) -> bool {
+		*method == Method::GET
+			// Replies to interactions are only seen by the user who used the command or clicked the button.
+			|| route.starts_with("/interactions/")
+			|| route.starts_with(&format!("/webhooks/{}/", self.application))
+			// Opening a direct message channel has no visible effect, unlike sending a message in it.
+			|| (*method == Method::POST && route == "/users/@me/channels")
+	}
+
+	async fn forward(&self, method: Method, uri: &Uri, mut headers: HeaderMap, body: Bytes) -> Response {
+		headers.remove(header::HOST);
+		let url = format!("{}{}", self.upstream, uri.path_and_query().map(|p| p.as_str()).unwrap_or("/"));
+
+		let result = self.client.request(method, url)
+			.headers(headers)
+			.body(body)
+			.send().await;
+		let response = match result {
+			Ok(response) => response,
+			Err(e) => {
+				error!(error = %e, "Couldn't send request to Discord.");
+				return StatusCode::BAD_GATEWAY.into_response();
+			},
+		};
+
+		let status = response.status();
+		let mut headers = response.headers().clone();
+		// The body is sent again in one piece.
+		headers.remove(header::TRANSFER_ENCODING);
+		headers.remove(header::CONTENT_LENGTH);
+		headers.remove(header::CONNECTION);
+
+		match response.bytes().await {
+			Ok(body) => (status, headers, body).into_response(),
+			Err(e) => {
+				error!(error = %e, "Couldn't read response from Discord.");
+				StatusCode::BAD_GATEWAY.into_response()
+			},
+		}
+	}
+
+	/// Answers a request that wasn't sent like Discord would have, as far as the bot cares.
+	fn pretend(&self, method: &Method, route: &str, payload: &str) -> Response {
+		let segments: Vec<&str> = route.trim_start_matches('/').split('/').collect();
+		match (method, segments.as_slice()) {
+			(&Method::POST, ["channels", channel, "messages"]) => {
+				let id = self.next_id.fetch_add(1, Ordering::Relaxed).to_string();
+				axum::Json(message(&id, channel, payload)).into_response()
+			},
+			(&Method::PATCH, ["channels", channel, "messages", id]) => {
+				axum::Json(message(id, channel, payload)).into_response()
+			},
+			// The commands are sent back as if they were registered.
+			(&Method::PUT, ["applications", _, "commands"] | ["applications", _, "guilds", _, "commands"]) => {
+				([(header::CONTENT_TYPE, "application/json")], String::from(payload)).into_response()
+			},
+			_ => StatusCode::NO_CONTENT.into_response(),
+		}
+	}
+}
+
+/// The JSON payload of a request, which is one of the parts of the body when files are sent with it.
+fn payload(headers: &HeaderMap, body: &[u8]) -> String {
+	let body = String::from_utf8_lossy(body);
+	let boundary = headers.get(header::CONTENT_TYPE)
+		.and_then(|v| v.to_str().ok())
+		.and_then(|v| v.strip_prefix("multipart/form-data; boundary="));
+
+	let boundary = match boundary {
+		Some(boundary) => boundary,
+		None => return body.into_owned(),
+	};
+
+	body.split(&format!("--{}", boundary))
+		.find(|part| part.contains("name=\"payload_json\""))
+		.and_then(|part| part.split_once("\r\n\r\n"))
+		.map(|(_, json)| String::from(json.trim_end_matches("\r\n")))
+		.unwrap_or_default()
+}
+
+/// A message as the API would have returned it, with the content that was sent.
+fn message(id: &str, channel: &str, payload: &str) -> Value {
+	let content = serde_json::from_str::<Value>(payload).ok()
+		.and_then(|p| p.get("content").cloned())
+		.filter(Value::is_string)
+		.unwrap_or_else(|| json!(""));
+
+	json!({
+		"id": id,
+		"channel_id": channel,
+		"author": {
+			"id": "1",
+			"username": "kodbot",
+			"discriminator": "0",
+			"avatar": null,
+		},
+		"content": content,
+		"timestamp": "1970-01-01T00:00:00.000000+00:00",
+		"edited_timestamp": null,
+		"tts": false,
+		"mention_everyone": false,
+		"mentions": [],
+		"mention_roles": [],
+		"attachments": [],
+		"embeds": [],
+		"components": [],
+		"pinned": false,
+		"type": 0,
+	})
+}
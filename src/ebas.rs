@@ -1,37 +1,93 @@
-use reqwest::{Client, Url};
+use reqwest::{Client, StatusCode, Url};
 use reqwest::header;
 
+use tracing::{debug, info};
+
 use std::sync::Arc;
 use crate::Context;
+use crate::config::Ebas;
+
+/// Something that went wrong when asking eBas, as opposed to eBas not finding the member.
+#[derive(Debug)]
+pub enum EbasError {
+	Request(reqwest::Error),
+	Body(StatusCode, reqwest::Error),
+	Parse(StatusCode, serde_json::Error, String),
+	Api(StatusCode, serde_json::Value),
+	Response(StatusCode, String),
+}
+
+impl std::fmt::Display for EbasError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			EbasError::Request(e) => write!(f, "couldn't send request to eBas: {}", e),
+			EbasError::Body(status, e) => write!(f, "couldn't get body from eBas response ({}): {}", status, e),
+			EbasError::Parse(status, e, body) => write!(f, "couldn't parse JSON from eBas response ({}): {}: {}", status, e, body),
+			EbasError::Api(status, e) => write!(f, "eBas returned an error ({}): {}", status, e),
+			EbasError::Response(status, body) => write!(f, "eBas response is missing member_found ({}): {}", status, body),
+		}
+	}
+}
 
-pub async fn verify_membership(context: Arc<Context>, email: String) -> bool {
+impl std::error::Error for EbasError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			EbasError::Request(e) | EbasError::Body(_, e) => Some(e),
+			EbasError::Parse(_, e, _) => Some(e),
+			_ => None,
+		}
+	}
+}
+
+/// Asks eBas whether someone with `email` is a member this year.
+pub async fn verify_membership(context: Arc<Context>, config: &Ebas, email: String) -> Result<bool, EbasError> {
 	let client = Client::new();
-	let mut url = Url::parse(&context.config.ebas().url()).expect("Couldn't parse URL for eBas.");
+	// SAFETY The secrets of every association in the configuration are read when starting.
+	let secrets = context.secrets.ebas(config.association()).expect("Missing eBas credentials.");
+	let mut url = Url::parse(config.url()).expect("Couldn't parse URL for eBas.");
 	url.path_segments_mut().expect("Couldn't get path segments for eBas URL.").push("confirm_membership.json");
+	let year = time::OffsetDateTime::now_utc().year();
 	let body = serde_json::json!({
 		"request" : {
 			"action" : "confirm_membership",
-			"association_number" : context.secrets.ebas.id,
-			"api_key" : context.secrets.ebas.api_key,
-			"year_id" : time::OffsetDateTime::now_utc().year(),
+			"association_number" : secrets.id,
+			"api_key" : secrets.api_key,
+			"year_id" : year,
 			"email": email,
 		}
 	}).to_string();
+
+	debug!(%url, year, "Sending membership request to eBas.");
+	let timer = context.metrics.ebas_duration().start_timer();
 	let request = client.post(url).body(body).header(header::CONTENT_TYPE, "application/json");
-	let response = request.send().await.expect("Couldn't send request to eBas.");
-	let text = response.text().await.expect("Couldn't get body from response.");
-	let json: serde_json::Value = serde_json::from_str(&text).expect("Couldn't parse JSON.");
+	let response = request.send().await.map_err(|e| {
+		context.metrics.ebas_error("request");
+		EbasError::Request(e)
+	})?;
+
+	let status = response.status();
+	let text = response.text().await.map_err(|e| {
+		context.metrics.ebas_error("body");
+		EbasError::Body(status, e)
+	})?;
+	timer.observe_duration();
+
+	let json: serde_json::Value = serde_json::from_str(&text).map_err(|e| {
+		context.metrics.ebas_error("parse");
+		EbasError::Parse(status, e, text.clone())
+	})?;
 	let response = &json["response"];
 
 	if !response["request_result"]["error"].is_null() {
-		// TODO Better error handling.
-		return false;
+		context.metrics.ebas_error("api");
+		return Err(EbasError::Api(status, response["request_result"]["error"].clone()));
 	}
 
 	if let Some(is_member) = response["member_found"].as_bool() {
-		is_member
+		info!(is_member, "eBas answered membership request.");
+		Ok(is_member)
 	} else {
-		// TODO Again, better error handling.
-		false
+		context.metrics.ebas_error("response");
+		Err(EbasError::Response(status, text))
 	}
-}
\ No newline at end of file
+}
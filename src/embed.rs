@@ -0,0 +1,263 @@
+use twilight_model::channel::message::Embed;
+use twilight_model::http::attachment::Attachment;
+use twilight_util::builder::embed::{EmbedBuilder, EmbedFieldBuilder, EmbedFooterBuilder, ImageSource};
+
+use serde::Deserialize;
+
+use std::path::{Path, PathBuf};
+
+/// The most embeds Discord allows in one message.
+pub const EMBEDS_PER_MESSAGE: usize = 10;
+
+/// The most characters Discord allows in all embeds of one message together.
+pub const TOTAL_LENGTH: usize = 6000;
+
+/// A file describing embeds, in TOML or, if the file name ends in `.json`, JSON.
+#[derive(Deserialize)]
+struct EmbedFile {
+	#[serde(default)]
+	embeds: Vec<EmbedDefinition>,
+}
+
+#[derive(Deserialize)]
+struct EmbedDefinition {
+	title: Option<String>,
+	description: Option<String>,
+	url: Option<String>,
+	#[serde(alias = "color")]
+	colour: Option<Colour>,
+	#[serde(default)]
+	fields: Vec<FieldDefinition>,
+	thumbnail: Option<String>,
+	image: Option<String>,
+	footer: Option<FooterDefinition>,
+}
+
+#[derive(Deserialize)]
+struct FieldDefinition {
+	name: String,
+	value: String,
+	#[serde(default)]
+	inline: bool,
+}
+
+#[derive(Deserialize)]
+struct FooterDefinition {
+	text: String,
+	icon: Option<String>,
+}
+
+/// Either a number such as `0x2b7bb9` or a hex string such as `"#2b7bb9"`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Colour {
+	Number(u32),
+	Hex(String),
+}
+
+/// An embed ready to be posted, together with the local files it shows.
+#[derive(Clone)]
+pub struct LoadedEmbed {
+	embed: Embed,
+	files: Vec<PathBuf>,
+}
+
+#[derive(Debug)]
+pub enum EmbedError {
+	Io(PathBuf, std::io::Error),
+	Parse(PathBuf, String),
+	Invalid(PathBuf, String),
+}
+
+impl std::fmt::Display for EmbedError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			EmbedError::Io(path, e) => write!(f, "couldn't read {}: {}", path.display(), e),
+			EmbedError::Parse(path, e) => write!(f, "couldn't parse {}: {}", path.display(), e),
+			EmbedError::Invalid(path, e) => write!(f, "invalid embed in {}: {}", path.display(), e),
+		}
+	}
+}
+
+impl std::error::Error for EmbedError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			EmbedError::Io(_, e) => Some(e),
+			_ => None,
+		}
+	}
+}
+
+impl Colour {
+	fn value(&self) -> Result<u32, String> {
+		match self {
+			Colour::Number(n) => Ok(*n),
+			Colour::Hex(s) => u32::from_str_radix(s.trim_start_matches('#'), 16)
+				.map_err(|_| format!("invalid colour {}", s)),
+		}
+	}
+}
+
+impl LoadedEmbed {
+	pub fn embed(&self) -> &Embed {
+		&self.embed
+	}
+
+	/// The local files the embed shows, which have to be uploaded with the message.
+	pub fn files(&self) -> &[PathBuf] {
+		&self.files
+	}
+
+	/// The number of characters that count towards [`TOTAL_LENGTH`].
+	pub fn length(&self) -> usize {
+		let embed = &self.embed;
+		let length = |s: &Option<String>| s.as_ref().map_or(0, |s| s.chars().count());
+
+		length(&embed.title)
+			+ length(&embed.description)
+			+ embed.footer.as_ref().map_or(0, |f| f.text.chars().count())
+			+ embed.fields.iter().map(|f| f.name.chars().count() + f.value.chars().count()).sum::<usize>()
+	}
+
+	/// Whether an embed of a posted message shows the same thing as this one.
+	///
+	/// Only the fields we set are compared, since Discord fills in the rest. Local files
+	/// are uploaded as attachments, so for those only the file name of the URL is compared.
+	pub fn matches(&self, posted: &Embed) -> bool {
+		let expected = &self.embed;
+
+		let fields = expected.fields.len() == posted.fields.len()
+			&& expected.fields.iter().zip(&posted.fields)
+				.all(|(a, b)| a.name == b.name && a.value == b.value && a.inline == b.inline);
+
+		let footer = match (&expected.footer, &posted.footer) {
+			(Some(a), Some(b)) => a.text == b.text && same_image(a.icon_url.as_deref(), b.icon_url.as_deref()),
+			(None, None) => true,
+			_ => false,
+		};
+
+		expected.title == posted.title
+			&& expected.description == posted.description
+			&& expected.url == posted.url
+			&& expected.color == posted.color
+			&& fields
+			&& footer
+			&& same_image(expected.thumbnail.as_ref().map(|t| t.url.as_str()), posted.thumbnail.as_ref().map(|t| t.url.as_str()))
+			&& same_image(expected.image.as_ref().map(|i| i.url.as_str()), posted.image.as_ref().map(|i| i.url.as_str()))
+	}
+}
+
+/// Reads the files to upload with a message, with ids in the order they are given.
+pub fn attachments(files: &[PathBuf]) -> Result<Vec<Attachment>, EmbedError> {
+	files.iter().zip(0..).map(|(path, id)| {
+		let file = std::fs::read(path).map_err(|e| EmbedError::Io(path.clone(), e))?;
+		Ok(Attachment::from_bytes(file_name(path), file, id))
+	}).collect()
+}
+
+/// Reads and validates the embeds in `path`.
+///
+/// Images that aren't URLs are paths to local files, which are uploaded with the message.
+pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Vec<LoadedEmbed>, EmbedError> {
+	let path = path.as_ref();
+	let s = read(path)?;
+	from_str(path, &s)
+}
+
+/// Reads an embed file without parsing it, to fill in placeholders first.
+pub fn read<P: AsRef<Path>>(path: P) -> Result<String, EmbedError> {
+	let path = path.as_ref();
+	std::fs::read_to_string(path).map_err(|e| EmbedError::Io(path.to_path_buf(), e))
+}
+
+/// Parses and validates the embeds in `s`, which was read from `path`.
+pub fn from_str(path: &Path, s: &str) -> Result<Vec<LoadedEmbed>, EmbedError> {
+	let file: EmbedFile = match path.extension().and_then(|e| e.to_str()) {
+		Some("json") => serde_json::from_str(s).map_err(|e| e.to_string()),
+		_ => toml::from_str(s).map_err(|e| e.to_string()),
+	}.map_err(|e| EmbedError::Parse(path.to_path_buf(), e))?;
+
+	file.embeds.into_iter()
+		.map(|e| build(e).map_err(|e| EmbedError::Invalid(path.to_path_buf(), e)))
+		.collect()
+}
+
+fn build(definition: EmbedDefinition) -> Result<LoadedEmbed, String> {
+	let mut files = Vec::new();
+	let mut image = |source: &str| -> Result<ImageSource, String> {
+		if source.starts_with("https://") || source.starts_with("http://") {
+			return ImageSource::url(source).map_err(|e| e.to_string());
+		}
+
+		let path = PathBuf::from(source);
+		std::fs::metadata(&path).map_err(|e| format!("image {}: {}", source, e))?;
+		let source = ImageSource::attachment(file_name(&path)).map_err(|e| format!("image {}: {}", source, e))?;
+		files.push(path);
+		Ok(source)
+	};
+
+	let mut builder = EmbedBuilder::new();
+
+	if let Some(title) = definition.title {
+		builder = builder.title(title);
+	}
+	if let Some(description) = definition.description {
+		builder = builder.description(description);
+	}
+	if let Some(url) = definition.url {
+		builder = builder.url(url);
+	}
+	if let Some(colour) = definition.colour {
+		builder = builder.color(colour.value()?);
+	}
+	for field in definition.fields {
+		let mut field_builder = EmbedFieldBuilder::new(field.name, field.value);
+		if field.inline {
+			field_builder = field_builder.inline();
+		}
+		builder = builder.field(field_builder);
+	}
+	if let Some(thumbnail) = definition.thumbnail {
+		builder = builder.thumbnail(image(&thumbnail)?);
+	}
+	if let Some(source) = definition.image {
+		builder = builder.image(image(&source)?);
+	}
+	if let Some(footer) = definition.footer {
+		let mut footer_builder = EmbedFooterBuilder::new(footer.text);
+		if let Some(icon) = footer.icon {
+			footer_builder = footer_builder.icon_url(image(&icon)?);
+		}
+		builder = builder.footer(footer_builder);
+	}
+
+	let embed = builder.validate().map_err(|e| e.to_string())?.build();
+
+	// The same file may be shown several times, but it's only uploaded once.
+	files.sort();
+	files.dedup();
+
+	Ok(LoadedEmbed {
+		embed,
+		files,
+	})
+}
+
+fn file_name(path: &Path) -> String {
+	path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default()
+}
+
+/// Compares an image we set with one of a posted embed, see [`LoadedEmbed::matches`].
+fn same_image(expected: Option<&str>, posted: Option<&str>) -> bool {
+	match (expected, posted) {
+		(Some(expected), Some(posted)) => match expected.strip_prefix("attachment://") {
+			Some(name) => {
+				let posted = posted.split(['?', '#']).next().unwrap_or_default();
+				posted == expected || posted.rsplit('/').next() == Some(name)
+			},
+			None => expected == posted,
+		},
+		(None, None) => true,
+		_ => false,
+	}
+}
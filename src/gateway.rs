@@ -0,0 +1,123 @@
+use twilight_gateway::{Shard, ShardId, Config, Event};
+use twilight_gateway::error::ReceiveMessageErrorType;
+use twilight_gateway::stream;
+use twilight_model::gateway::CloseCode;
+
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+use tracing::{debug, info, warn};
+
+use std::sync::Arc;
+
+use crate::{Context, module, shutdown};
+
+/// An error that reconnecting to the gateway won't fix, such as an invalid token.
+#[derive(Debug)]
+pub struct FatalError {
+	pub shard: ShardId,
+	pub close_code: CloseCode,
+}
+
+impl std::fmt::Display for FatalError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let hint = match self.close_code {
+			CloseCode::AuthenticationFailed => "the Discord token in the secrets is invalid",
+			CloseCode::DisallowedIntents => "the bot isn't allowed the Server Members Intent that the join module needs, turn it on under Bot in the Discord developer portal or turn the module off",
+			CloseCode::ShardingRequired => "the bot is in too many guilds for one shard, raise shards under [gateway]",
+			CloseCode::InvalidShard => "the shards are invalid, check shards under [gateway]",
+			_ => "this is a bug in the bot",
+		};
+		write!(f, "shard {} was closed by Discord with {} ({}): {}", self.shard.number(), self.close_code, self.close_code as u16, hint)
+	}
+}
+
+impl std::error::Error for FatalError {}
+
+/// Creates the shards set under `[gateway]`, with the intents of the enabled modules.
+pub(crate) fn shards(context: &Context) -> impl Iterator<Item = Shard> {
+	let config = context.config();
+	let total = config.gateway().shards();
+	let intents = module::intents(&config);
+	debug!(?intents, "Connecting with intents.");
+
+	let config = Config::new(context.secrets().discord.token.clone(), intents);
+	stream::create_range(0..total, total, config, |_, builder| builder.build())
+}
+
+/// Sends the events of a shard to `events` until `close` is cancelled, and then closes it.
+///
+/// The shard resumes its session when it reconnects, if Discord allows it. Since it only
+/// waits between failed attempts, the backoff under `[gateway]` is also waited out after
+/// each error in a row, so that a flaky connection doesn't reconnect in a tight loop.
+pub(crate) async fn run(mut shard: Shard, context: Arc<Context>, events: mpsc::UnboundedSender<Result<Event, FatalError>>, close: CancellationToken) {
+	let id = shard.id();
+	let mut errors = 0;
+
+	loop {
+		let result = tokio::select! {
+			result = shard.next_event() => result,
+			_ = close.cancelled() => break,
+		};
+
+		let error = match result {
+			Ok(event) => {
+				if matches!(event, Event::Ready(_) | Event::Resumed) {
+					errors = 0;
+				}
+				track_event(&context, id, &event);
+				// The receiver is only dropped when shutting down.
+				let _ = events.send(Ok(event));
+				continue;
+			},
+			Err(e) => e,
+		};
+
+		match error.kind() {
+			ReceiveMessageErrorType::FatallyClosed { close_code } => {
+				context.health.set_disconnected(id.number());
+				let _ = events.send(Err(FatalError { shard: id, close_code: *close_code }));
+				return;
+			},
+			ReceiveMessageErrorType::Io | ReceiveMessageErrorType::Reconnect | ReceiveMessageErrorType::SendingMessage => {
+				errors += 1;
+				context.health.set_disconnected(id.number());
+				let delay = context.config().gateway().backoff(errors);
+				warn!(error = %error, errors, ?delay, resume = shard.session().is_some(), "Lost the connection to the gateway, reconnecting.");
+
+				tokio::select! {
+					_ = tokio::time::sleep(delay) => (),
+					_ = close.cancelled() => break,
+				}
+			},
+			_ => warn!(error = %error, "Encountered error when receiving event."),
+		}
+	}
+
+	shutdown::close_shard(&mut shard).await;
+}
+
+fn track_event(context: &Context, shard: ShardId, event: &Event) {
+	context.health.event_received();
+
+	match event {
+		Event::Ready(_) => {
+			if context.health.set_connected(shard.number()) {
+				info!("Reconnected to the gateway with a new session.");
+				context.metrics.gateway_reconnected();
+			} else {
+				info!("Connected to the gateway.");
+			}
+		},
+		Event::Resumed => {
+			context.health.set_connected(shard.number());
+			info!("Reconnected to the gateway and resumed the session.");
+			context.metrics.gateway_reconnected();
+		},
+		Event::GatewayClose(frame) => {
+			warn!(?frame, "Gateway connection was closed.");
+			context.health.set_disconnected(shard.number());
+		},
+		_ => (),
+	}
+}
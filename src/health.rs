@@ -0,0 +1,78 @@
+use std::collections::BTreeSet;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicI64, Ordering};
+
+use serde::Serialize;
+
+/// Keeps track of the gateway connection so that it can be reported by `/healthz`.
+pub struct Health {
+	shards: u64,
+	// The shards that are connected, and the ones that have connected at some point.
+	connected: Mutex<BTreeSet<u64>>,
+	has_connected: Mutex<BTreeSet<u64>>,
+	// Unix timestamp in seconds, zero if no event has been received.
+	last_event: AtomicI64,
+}
+
+#[derive(Serialize)]
+pub struct Report {
+	pub healthy: bool,
+	pub gateway: GatewayReport,
+}
+
+#[derive(Serialize)]
+pub struct GatewayReport {
+	pub connected: bool,
+	pub shards: u64,
+	pub connected_shards: usize,
+	pub last_event: Option<String>,
+	pub seconds_since_last_event: Option<i64>,
+}
+
+impl Health {
+	pub fn new(shards: u64) -> Health {
+		Health {
+			shards,
+			connected: Mutex::new(BTreeSet::new()),
+			has_connected: Mutex::new(BTreeSet::new()),
+			last_event: AtomicI64::new(0),
+		}
+	}
+
+	/// Marks a shard as connected and returns whether this is a reconnection.
+	pub fn set_connected(&self, shard: u64) -> bool {
+		self.connected.lock().unwrap().insert(shard);
+		!self.has_connected.lock().unwrap().insert(shard)
+	}
+
+	pub fn set_disconnected(&self, shard: u64) {
+		self.connected.lock().unwrap().remove(&shard);
+	}
+
+	pub fn event_received(&self) {
+		let now = time::OffsetDateTime::now_utc().unix_timestamp();
+		self.last_event.store(now, Ordering::Relaxed);
+	}
+
+	pub fn report(&self) -> Report {
+		let connected_shards = self.connected.lock().unwrap().len();
+		let connected = connected_shards as u64 == self.shards;
+		let last_event = match self.last_event.load(Ordering::Relaxed) {
+			0 => None,
+			timestamp => time::OffsetDateTime::from_unix_timestamp(timestamp).ok(),
+		};
+		let now = time::OffsetDateTime::now_utc();
+
+		Report {
+			healthy: connected,
+			gateway: GatewayReport {
+				connected,
+				shards: self.shards,
+				connected_shards,
+				last_event: last_event
+					.and_then(|t| t.format(&time::format_description::well_known::Rfc3339).ok()),
+				seconds_since_last_event: last_event.map(|t| (now - t).whole_seconds()),
+			},
+		}
+	}
+}
@@ -0,0 +1,181 @@
+use twilight_model::application::command::{Command, CommandOption};
+
+use tracing::warn;
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::template::render;
+
+/// The message catalogs, by language. They are compiled into the bot.
+const CATALOGS: &[(&str, &str)] = &[
+	("en", include_str!("../locales/en.toml")),
+	("sv", include_str!("../locales/sv.toml")),
+];
+
+struct Catalog {
+	// The Discord locales that use the catalog, such as "sv-SE".
+	discord: Vec<String>,
+	// Messages by their dotted key, such as "member.verified".
+	messages: HashMap<String, String>,
+}
+
+fn catalogs() -> &'static HashMap<&'static str, Catalog> {
+	static LOADED: OnceLock<HashMap<&'static str, Catalog>> = OnceLock::new();
+	LOADED.get_or_init(|| CATALOGS.iter().map(|(language, source)| {
+		let table: toml::Table = toml::from_str(source).expect("Message catalog is malformed.");
+		let discord = table.get("discord")
+			.and_then(toml::Value::as_array)
+			.map(|a| a.iter().filter_map(|l| l.as_str().map(String::from)).collect())
+			.unwrap_or_default();
+
+		let mut messages = HashMap::new();
+		flatten("", &table, &mut messages);
+
+		(*language, Catalog { discord, messages })
+	}).collect())
+}
+
+fn flatten(prefix: &str, table: &toml::Table, messages: &mut HashMap<String, String>) {
+	for (key, value) in table {
+		let key = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+		match value {
+			toml::Value::String(s) => {
+				messages.insert(key, s.clone());
+			},
+			toml::Value::Table(t) => flatten(&key, t, messages),
+			_ => (),
+		}
+	}
+}
+
+/// The locales that Discord clients use, see https://discord.com/developers/docs/reference#locales.
+const DISCORD_LOCALES: &[&str] = &[
+	"id", "da", "de", "en-GB", "en-US", "es-ES", "es-419", "fr", "hr", "it", "lt", "hu", "nl", "no", "pl",
+	"pt-BR", "ro", "fi", "sv-SE", "vi", "tr", "cs", "el", "bg", "ru", "uk", "hi", "th", "zh-CN", "ja", "zh-TW", "ko",
+];
+
+/// Whether Discord uses the locale, such as `sv-SE`, and not for example `sv` or `sv_SE`.
+pub fn is_discord_locale(locale: &str) -> bool {
+	DISCORD_LOCALES.contains(&locale)
+}
+
+/// Whether there is a catalog for the language.
+pub fn is_supported(language: &str) -> bool {
+	catalogs().contains_key(language)
+}
+
+/// Finds the catalog for a Discord locale such as `sv-SE`, by the locale or by its language.
+fn catalog(locale: &str) -> Option<&'static Catalog> {
+	let catalogs = catalogs();
+	catalogs.values().find(|c| c.discord.iter().any(|l| l == locale))
+		.or_else(|| catalogs.get(locale.split('-').next().unwrap_or(locale)))
+}
+
+/// Looks up the message `key` in the language of `locale`, or in `fallback` if the locale
+/// isn't supported or lacks the message, and fills in the placeholders.
+pub fn text(locale: Option<&str>, fallback: &str, key: &str, placeholders: &[(&str, String)]) -> String {
+	let message = locale.and_then(catalog)
+		.and_then(|c| c.messages.get(key))
+		.or_else(|| catalogs().get(fallback).and_then(|c| c.messages.get(key)));
+
+	match message {
+		Some(message) => render(message, placeholders),
+		None => {
+			warn!(key, "Message is missing from the catalogs.");
+			String::from(key)
+		},
+	}
+}
+
+/// Adds the translated names and descriptions from the catalogs to the commands.
+pub fn localize(commands: &mut [Command]) {
+	for command in commands {
+		let path = format!("commands.{}", command.name);
+		command.name_localizations = localizations(&format!("{}.name", path));
+		command.description_localizations = localizations(&format!("{}.description", path));
+		localize_options(&path, &mut command.options);
+	}
+}
+
+fn localize_options(prefix: &str, options: &mut [CommandOption]) {
+	for option in options {
+		let path = format!("{}.{}", prefix, option.name);
+		option.name_localizations = localizations(&format!("{}.name", path));
+		option.description_localizations = localizations(&format!("{}.description", path));
+		if let Some(options) = &mut option.options {
+			localize_options(&path, options);
+		}
+	}
+}
+
+/// The message `key` by Discord locale, for the catalogs that have it.
+fn localizations(key: &str) -> Option<HashMap<String, String>> {
+	let localizations: HashMap<String, String> = catalogs().values()
+		.filter_map(|c| c.messages.get(key).map(|m| (c, m)))
+		.flat_map(|(c, m)| c.discord.iter().map(move |l| (l.clone(), m.clone())))
+		.collect();
+
+	(!localizations.is_empty()).then_some(localizations)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use std::collections::BTreeSet;
+
+	/// The keys of the messages, without the command names, which are only translated from English.
+	fn keys(language: &str) -> BTreeSet<&'static str> {
+		catalogs()[language].messages.keys()
+			.map(String::as_str)
+			.filter(|k| !k.starts_with("commands."))
+			.collect()
+	}
+
+	#[test]
+	fn catalogs_have_the_same_keys() {
+		let fallback = keys("en");
+		for (language, _) in CATALOGS {
+			let keys = keys(language);
+			assert_eq!(
+				keys.symmetric_difference(&fallback).collect::<Vec<_>>(),
+				Vec::<&&str>::new(),
+				"{} and en have different messages",
+				language,
+			);
+		}
+	}
+
+	/// Finds the string literals in the source that look like message keys, such as
+	/// `"member.verified"`, and checks that the fallback catalog has them.
+	#[test]
+	fn used_keys_are_in_catalog() {
+		let messages = &catalogs()["en"].messages;
+		let sections: BTreeSet<&str> = messages.keys().filter_map(|k| k.split('.').next()).collect();
+
+		let src = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+		let mut files = vec![src];
+		let mut missing = Vec::new();
+		while let Some(path) = files.pop() {
+			if path.is_dir() {
+				files.extend(std::fs::read_dir(&path).unwrap().map(|e| e.unwrap().path()));
+				continue;
+			}
+
+			let source = std::fs::read_to_string(&path).unwrap();
+			for literal in source.split('"').skip(1).step_by(2) {
+				let key = match literal.split_once('.') {
+					Some((section, rest)) if sections.contains(section) && !rest.is_empty()
+						&& literal.chars().all(|c| c.is_ascii_lowercase() || c == '_' || c == '.') => literal,
+					_ => continue,
+				};
+				if !messages.contains_key(key) {
+					missing.push(format!("{}: {}", path.display(), key));
+				}
+			}
+		}
+
+		assert!(missing.is_empty(), "messages missing from en.toml: {:#?}", missing);
+	}
+}
@@ -0,0 +1,130 @@
+use twilight_http::Client;
+use twilight_model::gateway::payload::incoming::MemberAdd;
+use twilight_model::id::Id;
+use twilight_model::id::marker::ChannelMarker;
+
+use twilight_gateway::{Event, Intents};
+
+use vesper::framework::Framework;
+
+use async_trait::async_trait;
+
+use tracing::{debug, info, warn, error};
+
+use std::sync::Arc;
+
+use crate::Context;
+use crate::config::Template;
+use crate::module::Module;
+use crate::template::{render, Resolver};
+
+/// The direct message and greeting sent when someone joins a guild.
+pub struct JoinModule;
+
+#[async_trait]
+impl Module for JoinModule {
+	fn name(&self) -> &'static str {
+		"join"
+	}
+
+	fn intents(&self) -> Intents {
+		// NOTE GUILD_MEMBERS is a privileged intent that has to be enabled for the bot in the developer portal.
+		Intents::GUILD_MEMBERS
+	}
+
+	async fn event(&self, framework: &Framework<Arc<Context>>, event: &Event) {
+		if let Event::MemberAdd(member) = event {
+			handle_member_add(framework.http_client(), Arc::clone(&framework.data), member).await;
+		}
+	}
+}
+
+/// Sends the configured direct message and greeting when someone joins a guild.
+// NOTE This requires the GUILD_MEMBERS privileged intent.
+pub async fn handle_member_add(client: &Client, context: Arc<Context>, event: &MemberAdd) {
+	let user = &event.member.user;
+	if user.bot {
+		return;
+	}
+
+	let config = match context.config().guild(event.guild_id) {
+		Some(config) => config,
+		None => return,
+	};
+	let join = config.join();
+	if join.dm().is_none() && join.greeting().is_none() {
+		return;
+	}
+
+	info!(user = %user.id, guild = %event.guild_id, "Member joined.");
+
+	let guild = match client.guild(event.guild_id).with_counts(true).await {
+		Ok(response) => match response.model().await {
+			Ok(guild) => guild,
+			Err(e) => {
+				error!(error = %e, "Couldn't deserialize guild.");
+				return;
+			},
+		},
+		Err(e) => {
+			error!(error = %e, "Couldn't fetch guild.");
+			return;
+		},
+	};
+
+	let placeholders = [
+		("user", format!("<@{}>", user.id)),
+		("guild", guild.name),
+		("member_count", guild.approximate_member_count.map(|c| c.to_string()).unwrap_or_default()),
+	];
+
+	let resolver = Resolver::new(client, &config);
+
+	if let Some(template) = join.dm() {
+		// NOTE Members can turn off direct messages from servers, so this may fail.
+		match client.create_private_channel(user.id).await {
+			Ok(response) => match response.model().await {
+				Ok(channel) => send(client, &resolver, channel.id, template, &placeholders, "direct message").await,
+				Err(e) => error!(error = %e, "Couldn't deserialize private channel."),
+			},
+			Err(e) => warn!(user = %user.id, error = %e, "Couldn't open private channel."),
+		}
+	}
+
+	if let Some(greeting) = join.greeting() {
+		send(client, &resolver, greeting.channel(), greeting.template(), &placeholders, "greeting").await;
+	}
+}
+
+// NOTE This requires the SEND_MESSAGES permission for channels in the guild.
+async fn send(client: &Client, resolver: &Resolver<'_>, channel: Id<ChannelMarker>, template: &Template, placeholders: &[(&str, String)], kind: &str) {
+	let content = match template.content() {
+		Ok(content) => content,
+		Err(e) => {
+			error!(kind, error = %e, "Couldn't read join message.");
+			return;
+		},
+	};
+
+	// The guild's placeholders are filled in first, so that names from users are left as they are.
+	let content = match resolver.render(&content).await {
+		Ok(content) => render(&content, placeholders),
+		Err(e) => {
+			error!(kind, error = %e, "Couldn't fill in join message.");
+			return;
+		},
+	};
+
+	let request = match client.create_message(channel).content(&content) {
+		Ok(request) => request,
+		Err(e) => {
+			error!(kind, error = %e, "Join message is malformed.");
+			return;
+		},
+	};
+
+	match request.await {
+		Ok(_) => debug!(%channel, kind, "Sent join message."),
+		Err(e) => warn!(%channel, kind, error = %e, "Couldn't send join message."),
+	}
+}
@@ -0,0 +1,676 @@
+// The code generated by vesper for commands with checks trips this lint.
+#![allow(clippy::unused_unit)]
+
+use twilight_http::Client;
+use twilight_http::error::ErrorType;
+use twilight_gateway::Event;
+use twilight_model::channel::message::MessageFlags;
+use twilight_model::application::interaction::{Interaction, InteractionData, InteractionType};
+use twilight_model::application::interaction::application_command::CommandOptionValue;
+use twilight_model::http::interaction::{InteractionResponse, InteractionResponseType, InteractionResponseData};
+use twilight_model::guild::Member;
+use twilight_model::id::Id;
+use twilight_model::id::marker::GuildMarker;
+
+use vesper::framework::{Framework, ProcessResult, DefaultError};
+use vesper::command::ExecutionState;
+use vesper::context::SlashContext;
+
+use tracing::{debug, info, warn, error, Instrument};
+
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
+
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+use std::path::PathBuf;
+
+pub mod config;
+pub mod state;
+pub mod secrets;
+pub mod module;
+pub mod dry_run;
+#[cfg(feature = "member")]
+mod member;
+pub mod welcome;
+pub mod posted;
+#[cfg(feature = "sticky")]
+pub mod sticky;
+pub mod embed;
+mod roles;
+mod rules;
+#[cfg(feature = "join")]
+mod join;
+pub mod template;
+mod i18n;
+pub mod ebas;
+pub mod logging;
+mod metrics;
+mod health;
+mod server;
+mod shutdown;
+pub mod gateway;
+mod reload;
+pub mod check;
+pub mod commands;
+
+use config::Permission;
+
+/// Everything the handlers of the bot share.
+pub struct Context {
+	config: RwLock<Arc<config::Config>>,
+	secrets: secrets::Secrets,
+	state: Box<dyn state::Storage>,
+	metrics: metrics::Metrics,
+	health: health::Health,
+	// Held while a welcome or sticky message is synced, so that two syncs don't both post it.
+	sync: tokio::sync::Mutex<()>,
+}
+
+impl Context {
+	/// The current configuration, which may be replaced when it's reloaded.
+	pub fn config(&self) -> Arc<config::Config> {
+		Arc::clone(&self.config.read().unwrap())
+	}
+
+	pub fn set_config(&self, config: config::Config) {
+		*self.config.write().unwrap() = Arc::new(config);
+	}
+
+	pub fn secrets(&self) -> &secrets::Secrets {
+		&self.secrets
+	}
+
+	pub fn state(&self) -> &dyn state::Storage {
+		self.state.as_ref()
+	}
+
+	/// Looks up a message in the language of the user, see [`i18n::text`].
+	pub fn text(&self, locale: Option<&str>, key: &str, placeholders: &[(&str, String)]) -> String {
+		i18n::text(locale, self.config().locale().fallback(), key, placeholders)
+	}
+}
+
+/// Looks up the configuration of the guild that the interaction was sent from.
+/// If the guild isn't configured, the user is told so and `None` is returned.
+pub(crate) async fn guild_config(ctx: &SlashContext<'_, Arc<Context>>) -> Option<Arc<config::Guild>> {
+	let config = ctx.interaction.guild_id.and_then(|guild| ctx.data.config().guild(guild));
+
+	if config.is_none() {
+		warn!(guild = ?ctx.interaction.guild_id, "Command was used outside of a configured guild.");
+		let response = InteractionResponse {
+			kind: InteractionResponseType::ChannelMessageWithSource,
+			data: Some(InteractionResponseData {
+				content: Some(ctx.data.text(ctx.interaction.locale.as_deref(), "general.unconfigured_guild", &[])),
+				flags: Some(MessageFlags::EPHEMERAL),
+				..Default::default()
+			}),
+		};
+
+		let r = ctx.interaction_client.create_response(ctx.interaction.id, &ctx.interaction.token, &response).await;
+		if let Err(e) = r {
+			error!(error = %e, "Couldn't respond to command.");
+		}
+	}
+
+	config
+}
+
+/// Checks whether the user who sent the command is in `permissions`, and tells them if they aren't.
+pub(crate) async fn has_permission(ctx: &SlashContext<'_, Arc<Context>>, config: &config::Guild, permissions: &[Permission]) -> bool {
+	let user = match (&ctx.interaction.user, &ctx.interaction.member) {
+		(Some(user), _) => user.id,
+		(_, Some(member)) => match &member.user {
+			Some(user) => user.id,
+			None => panic!("User data in member should be set!"),
+		},
+		(None, None) => panic!("Either user or member should be set!"),
+	};
+
+	let roles = if let Some(member) = &ctx.interaction.member {
+		member.roles.clone()
+	} else {
+		let member = ctx.http_client()
+			.guild_member(config.id(), user).await
+			.expect("Couldn't fetch member.")
+			.model().await
+			.expect("Couldn't serialize member.");
+
+		member.roles
+	};
+
+	let permitted = permissions.iter().any(|p| match p {
+		Permission::User(u) => &user == u,
+		Permission::Role(r) => roles.contains(r),
+	});
+
+	if !permitted {
+		info!(%user, "Denied permission to run command.");
+		let response = InteractionResponse {
+			kind: InteractionResponseType::ChannelMessageWithSource,
+			data: Some(InteractionResponseData {
+				content: Some(ctx.data.text(ctx.interaction.locale.as_deref(), "general.no_permission", &[])),
+				flags: Some(MessageFlags::EPHEMERAL),
+				..Default::default()
+			}),
+		};
+
+		let r = ctx.interaction_client.create_response(ctx.interaction.id, &ctx.interaction.token, &response).await;
+		if let Err(e) = r {
+			error!(error = %e, "Couldn't respond to command.");
+		}
+	}
+
+	permitted
+}
+
+/// Why the members of a guild couldn't be listed.
+#[derive(Debug)]
+pub(crate) enum MembersError {
+	Http(Box<twilight_http::Error>),
+	Deserialize(Box<twilight_http::response::DeserializeBodyError>),
+}
+
+impl std::fmt::Display for MembersError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			MembersError::Http(e) => write!(f, "couldn't get members: {}", e),
+			MembersError::Deserialize(e) => write!(f, "couldn't deserialize members: {}", e),
+		}
+	}
+}
+
+/// Lists every member of the guild, a page at a time.
+// NOTE This requires the GUILD_MEMBERS priviledged intent.
+pub(crate) async fn all_members(client: &Client, guild: Id<GuildMarker>) -> Result<Vec<Member>, MembersError> {
+	const MEMBER_LIMIT: u16 = 1000;
+	let mut members = Vec::new();
+	let mut after = None;
+
+	loop {
+		let mut req = client.guild_members(guild);
+		req = req.limit(MEMBER_LIMIT).expect("Invalid limit.");
+		if let Some(id) = after {
+			req = req.after(id);
+		}
+
+		let mut page = req.await.map_err(|e| MembersError::Http(Box::new(e)))?
+			.models().await.map_err(|e| MembersError::Deserialize(Box::new(e)))?;
+		debug!(count = page.len(), "Fetched page of guild members.");
+
+		// If we received less members than we requested,
+		// then we won't get any more in a subsequent request.
+		let done = page.len() < MEMBER_LIMIT as usize;
+		after = page.last().map(|member| member.user.id);
+		members.append(&mut page);
+
+		if done {
+			return Ok(members);
+		}
+	}
+}
+
+/// Builds the framework with the commands of the enabled modules.
+fn framework(client: Arc<Client>, context: Arc<Context>) -> Arc<Framework<Arc<Context>>> {
+	let application = context.secrets.discord.application;
+	let config = context.config();
+	let builder = Framework::builder(client, application, context);
+	Arc::new(module::enabled(&config).fold(builder, |builder, module| module.commands(builder)).build())
+}
+
+/// Sets up the bot, reading the configuration, secrets and state from files unless
+/// they are given directly.
+pub struct Builder {
+	config_path: PathBuf,
+	secrets_path: PathBuf,
+	state_path: PathBuf,
+	config: Option<config::Config>,
+	secrets: Option<secrets::Secrets>,
+	state: Option<Box<dyn state::Storage>>,
+	client: Option<Arc<Client>>,
+	logging: bool,
+	dry_run: bool,
+}
+
+#[derive(Debug)]
+pub enum BuildError {
+	Config(config::ConfigError),
+	Secrets(secrets::SecretsError),
+	State(state::StateError),
+	DryRun(std::io::Error),
+}
+
+impl std::fmt::Display for BuildError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			BuildError::Config(e) => write!(f, "couldn't read configuration: {}", e),
+			BuildError::Secrets(e) => write!(f, "couldn't read secrets: {}", e),
+			BuildError::State(e) => write!(f, "couldn't open state: {}", e),
+			BuildError::DryRun(e) => write!(f, "couldn't start dry run: {}", e),
+		}
+	}
+}
+
+impl std::error::Error for BuildError {}
+
+/// Why [`Bot::run`] stopped without being told to shut down.
+#[derive(Debug)]
+pub enum RunError {
+	/// Discord rejected the token in the secrets.
+	InvalidToken,
+	/// The commands couldn't be registered in a guild.
+	Commands(Id<GuildMarker>, DefaultError),
+	Gateway(gateway::FatalError),
+}
+
+impl std::fmt::Display for RunError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			RunError::InvalidToken => write!(f, "Discord rejected the token in the secrets"),
+			RunError::Commands(guild, e) => write!(f, "couldn't register commands in guild {}: {}", guild, e),
+			RunError::Gateway(e) => write!(f, "{}", e),
+		}
+	}
+}
+
+impl std::error::Error for RunError {}
+
+impl Default for Builder {
+	fn default() -> Builder {
+		Builder {
+			config_path: PathBuf::from(config::DEFAULT_PATH),
+			secrets_path: PathBuf::from(secrets::DEFAULT_PATH),
+			state_path: PathBuf::from(state::DEFAULT_PATH),
+			config: None,
+			secrets: None,
+			state: None,
+			client: None,
+			logging: true,
+			dry_run: false,
+		}
+	}
+}
+
+impl Builder {
+	/// The configuration file, which is also watched for changes while running.
+	pub fn config_path<P: Into<PathBuf>>(mut self, path: P) -> Builder {
+		self.config_path = path.into();
+		self
+	}
+
+	pub fn secrets_path<P: Into<PathBuf>>(mut self, path: P) -> Builder {
+		self.secrets_path = path.into();
+		self
+	}
+
+	pub fn state_path<P: Into<PathBuf>>(mut self, path: P) -> Builder {
+		self.state_path = path.into();
+		self
+	}
+
+	/// Uses this configuration instead of reading it, which also means it isn't reloaded.
+	pub fn config(mut self, config: config::Config) -> Builder {
+		self.config = Some(config);
+		self
+	}
+
+	pub fn secrets(mut self, secrets: secrets::Secrets) -> Builder {
+		self.secrets = Some(secrets);
+		self
+	}
+
+	pub fn state(mut self, state: Box<dyn state::Storage>) -> Builder {
+		self.state = Some(state);
+		self
+	}
+
+	/// The client for the Discord API, by default one with the token from the secrets.
+	pub fn client(mut self, client: Arc<Client>) -> Builder {
+		self.client = Some(client);
+		self
+	}
+
+	/// Whether to set up logging as configured, which can only be done once per process.
+	pub fn logging(mut self, logging: bool) -> Builder {
+		self.logging = logging;
+		self
+	}
+
+	/// Only logs the requests that would change something in the guilds, and keeps the state in
+	/// memory, see [`dry_run::start`]. Has no effect on a client given with [`Builder::client`].
+	pub fn dry_run(mut self, dry_run: bool) -> Builder {
+		self.dry_run = dry_run;
+		self
+	}
+
+	pub fn build(self) -> Result<Bot, BuildError> {
+		let (config, config_path) = match self.config {
+			Some(config) => (config, None),
+			None => (config::from_file(&self.config_path).map_err(BuildError::Config)?, Some(self.config_path)),
+		};
+
+		if self.logging {
+			logging::init(config.log());
+		}
+
+		let guilds: Vec<_> = config.guilds().iter().map(|g| g.id()).collect();
+		let migration = state::MigrationContext::new(guilds.clone());
+		let state = match self.state {
+			Some(state) => state,
+			None => {
+				let backend = state::backend(&self.state_path, config.state().backend());
+				state::open(&self.state_path, backend, &migration).map_err(BuildError::State)?
+			},
+		};
+
+		// Messages that were never posted mustn't end up in the real state.
+		let state = if self.dry_run {
+			let memory = state::sqlite::SqliteStorage::open(":memory:", &migration).map_err(BuildError::State)?;
+			state::copy(state.as_ref(), &memory, &guilds).map_err(BuildError::State)?;
+			Box::new(memory)
+		} else {
+			state
+		};
+
+		let secrets = match self.secrets {
+			Some(secrets) => secrets,
+			None => secrets::load(&self.secrets_path, &config.associations()).map_err(BuildError::Secrets)?,
+		};
+
+		let client = match self.client {
+			Some(client) => client,
+			None if self.dry_run => {
+				let proxy = dry_run::start(secrets.discord.application, dry_run::DISCORD).map_err(BuildError::DryRun)?;
+				warn!(%proxy, "Dry run, changes to the guilds are logged instead of made.");
+				Arc::new(Client::builder()
+					.token(secrets.discord.token.clone())
+					.proxy(proxy.to_string(), true)
+					.build())
+			},
+			None => Arc::new(Client::new(secrets.discord.token.clone())),
+		};
+
+		let health = health::Health::new(config.gateway().shards());
+		let context = Arc::new(Context {
+			config: RwLock::new(Arc::new(config)),
+			secrets,
+			state,
+			metrics: metrics::Metrics::new(),
+			health,
+			sync: tokio::sync::Mutex::new(()),
+		});
+
+		let framework = framework(Arc::clone(&client), Arc::clone(&context));
+
+		Ok(Bot {
+			config_path,
+			context,
+			client,
+			framework,
+		})
+	}
+}
+
+/// The bot, ready to connect to Discord with [`Bot::run`].
+pub struct Bot {
+	// Where the configuration was read from, if it was, so it can be reloaded.
+	config_path: Option<PathBuf>,
+	context: Arc<Context>,
+	client: Arc<Client>,
+	framework: Arc<Framework<Arc<Context>>>,
+}
+
+impl Bot {
+	pub fn builder() -> Builder {
+		Builder::default()
+	}
+
+	pub fn context(&self) -> &Arc<Context> {
+		&self.context
+	}
+
+	pub fn client(&self) -> &Arc<Client> {
+		&self.client
+	}
+
+	pub fn framework(&self) -> &Arc<Framework<Arc<Context>>> {
+		&self.framework
+	}
+
+	/// Handles an event as if it was received from the gateway.
+	pub fn handle_event(&self, event: Event) -> impl Future<Output = ()> + Send + 'static {
+		event_handler(event, Arc::clone(&self.framework))
+	}
+
+	/// Lists, registers or clears the commands, see [`commands::run`].
+	pub async fn commands(&self, action: commands::CommandAction, scope: commands::Scope) -> Result<(), DefaultError> {
+		commands::run(&self.framework, action, scope).await
+	}
+
+	/// Posts the welcome and sticky messages, registers the commands and handles events
+	/// from the gateway until the process is told to shut down.
+	///
+	/// Returns an error if Discord rejects the token or closes the gateway connection for
+	/// a reason that reconnecting won't fix, after shutting down like it was told to.
+	pub async fn run(self) -> Result<(), RunError> {
+		let Bot { config_path, context, client, framework } = self;
+
+		// An invalid token would otherwise first show up as failed requests when syncing the guilds.
+		match client.current_user().await {
+			Ok(_) => (),
+			Err(e) if matches!(e.kind(), ErrorType::Response { status, .. } if status.get() == 401) => {
+				error!("Discord rejected the token in the secrets, shutting down.");
+				return Err(RunError::InvalidToken);
+			},
+			// Anything else is left to the gateway, which reconnects until Discord can be reached.
+			Err(e) => warn!(error = %e, "Couldn't check the Discord token."),
+		}
+
+		if let Some(http) = context.config().http() {
+			tokio::spawn(server::serve(http.address(), Arc::clone(&context)));
+		}
+
+		let config = context.config();
+		let modules: Vec<_> = module::enabled(&config).map(|m| m.name()).collect();
+		info!(?modules, "Starting.");
+
+		for guild in config.guilds() {
+			for module in module::enabled(&config) {
+				module.sync(&client, &context, guild).await;
+			}
+
+			if let Err(e) = commands::sync(&framework, guild.id()).await {
+				let e = RunError::Commands(guild.id(), e);
+				error!(error = %e, "Couldn't register commands, shutting down.");
+				if let Err(e) = context.state.flush() {
+					error!(error = %e, "Couldn't write state!");
+				}
+				return Err(e);
+			}
+		}
+
+		if let Some(config_path) = config_path {
+			tokio::spawn(reload::watch(config_path, Arc::clone(&client), Arc::clone(&context)));
+		}
+
+		let (sender, mut events) = mpsc::unbounded_channel();
+		let close = CancellationToken::new();
+		let shards = TaskTracker::new();
+		for shard in gateway::shards(&context) {
+			let span = tracing::info_span!("shard", id = shard.id().number());
+			shards.spawn(gateway::run(shard, Arc::clone(&context), sender.clone(), close.clone()).instrument(span));
+		}
+		drop(sender);
+
+		let tasks = TaskTracker::new();
+		let mut shutdown = std::pin::pin!(shutdown::signal());
+		let mut fatal = None;
+
+		loop {
+			let event = tokio::select! {
+				event = events.recv() => event,
+				_ = &mut shutdown => break,
+			};
+
+			match event {
+				Some(Ok(event)) => {
+					tasks.spawn(event_handler(event, Arc::clone(&framework)));
+				},
+				Some(Err(e)) => {
+					error!(error = %e, "Encountered fatal error on the gateway, shutting down.");
+					fatal = Some(e);
+					break;
+				},
+				// The shards only stop on their own after a fatal error, which is sent first.
+				None => break,
+			}
+		}
+
+		drain(&mut events, fatal.is_none(), &tasks, &framework).await;
+
+		close.cancel();
+		shards.close();
+		shards.wait().await;
+
+		if let Err(e) = context.state.flush() {
+			error!(error = %e, "Couldn't write state!");
+		}
+
+		info!("Shut down.");
+
+		match fatal {
+			Some(e) => Err(RunError::Gateway(e)),
+			None => Ok(()),
+		}
+	}
+}
+
+/// Waits for running tasks to finish, or until the shutdown timeout has passed.
+///
+/// New commands are turned away in the meantime, but other interactions are still
+/// processed, since running commands may be waiting for a button to be pressed.
+async fn drain(events: &mut mpsc::UnboundedReceiver<Result<Event, gateway::FatalError>>, mut receive: bool, tasks: &TaskTracker, framework: &Arc<Framework<Arc<Context>>>) {
+	tasks.close();
+	if tasks.is_empty() {
+		return;
+	}
+
+	let timeout = framework.data.config().shutdown().timeout();
+	info!(tasks = tasks.len(), ?timeout, "Waiting for running tasks to finish.");
+
+	let deadline = tokio::time::sleep(timeout);
+	tokio::pin!(deadline);
+
+	loop {
+		tokio::select! {
+			_ = tasks.wait() => {
+				info!("All running tasks have finished.");
+				return;
+			},
+			_ = &mut deadline => {
+				warn!(tasks = tasks.len(), "Timed out waiting for running tasks.");
+				return;
+			},
+			event = events.recv(), if receive => match event {
+				Some(Ok(Event::InteractionCreate(interaction))) if interaction.kind == InteractionType::ApplicationCommand => {
+					reject_interaction(framework, &interaction).await;
+				},
+				Some(Ok(event)) => {
+					tasks.spawn(event_handler(event, Arc::clone(framework)));
+				},
+				Some(Err(e)) => {
+					error!(error = %e, "Encountered fatal error on the gateway.");
+					receive = false;
+				},
+				None => receive = false,
+			},
+		}
+	}
+}
+
+async fn reject_interaction(framework: &Framework<Arc<Context>>, interaction: &Interaction) {
+	debug!(id = %interaction.id, "Rejecting command during shutdown.");
+
+	let response = InteractionResponse {
+		kind: InteractionResponseType::ChannelMessageWithSource,
+		data: Some(InteractionResponseData {
+			content: Some(framework.data.text(interaction.locale.as_deref(), "general.restarting", &[])),
+			flags: Some(MessageFlags::EPHEMERAL),
+			..Default::default()
+		}),
+	};
+
+	let r = framework.interaction_client().create_response(interaction.id, &interaction.token, &response).await;
+	if let Err(e) = r {
+		error!(error = %e, "Couldn't respond to command.");
+	}
+}
+
+async fn event_handler(event: Event, framework: Arc<Framework<Arc<Context>>>) {
+	if let Event::InteractionCreate(interaction) = event {
+		interaction_handler(interaction.0, framework).await;
+		return;
+	}
+
+	let config = framework.data.config();
+	for module in module::enabled(&config) {
+		module.event(&framework, &event).await;
+	}
+}
+
+async fn interaction_handler(interaction: Interaction, framework: Arc<Framework<Arc<Context>>>) {
+	let name = interaction_name(&interaction);
+	let span = tracing::info_span!(
+		"interaction",
+		id = %interaction.id,
+		user = interaction.author_id().map(tracing::field::display),
+		command = name.as_deref(),
+	);
+
+	let module = match &interaction.data {
+		Some(InteractionData::MessageComponent(data)) => module::enabled(&framework.data.config()).find(|m| m.handles_component(&data.custom_id)),
+		_ => None,
+	};
+	if let Some(module) = module {
+		module.component(&framework, interaction).instrument(span).await;
+		return;
+	}
+
+	let result = framework.process(interaction).instrument(span).await;
+	if let ProcessResult::CommandExecuted(result) = result {
+		let outcome = match result.state {
+			ExecutionState::CommandFinished => "success",
+			ExecutionState::CheckFailed => "denied",
+			ExecutionState::BeforeHookFailed => "skipped",
+			_ => "error",
+		};
+		// SAFETY Executed commands are application commands, which always have a name.
+		framework.data.metrics.command(&name.unwrap(), outcome);
+	}
+}
+
+/// Returns a human readable name for what the interaction invokes, e.g. `member verify`
+/// for a subcommand or the custom id for a component.
+fn interaction_name(interaction: &Interaction) -> Option<String> {
+	match interaction.data.as_ref()? {
+		InteractionData::ApplicationCommand(data) => {
+			let mut name = data.name.clone();
+			let mut options = &data.options;
+			while let Some(option) = options.first() {
+				match &option.value {
+					CommandOptionValue::SubCommand(inner) | CommandOptionValue::SubCommandGroup(inner) => {
+						name.push(' ');
+						name.push_str(&option.name);
+						options = inner;
+					},
+					_ => break,
+				}
+			}
+			Some(name)
+		},
+		InteractionData::MessageComponent(data) => Some(data.custom_id.clone()),
+		InteractionData::ModalSubmit(data) => Some(data.custom_id.clone()),
+		_ => None,
+	}
+}
\ No newline at end of file
@@ -0,0 +1,20 @@
+use tracing_subscriber::EnvFilter;
+use tracing_subscriber::fmt;
+
+use crate::config::{Log, LogFormat};
+
+/// Installs the global tracing subscriber.
+///
+/// The `RUST_LOG` environment variable takes precedence over the configured level,
+/// which makes it possible to turn up the verbosity without touching the configuration.
+pub fn init(config: &Log) {
+	let filter = EnvFilter::try_from_default_env()
+		.unwrap_or_else(|_| EnvFilter::new(config.level()));
+
+	let builder = fmt().with_env_filter(filter);
+
+	match config.format() {
+		LogFormat::Text => builder.init(),
+		LogFormat::Json => builder.json().init(),
+	}
+}
@@ -0,0 +1,384 @@
+use twilight_model::channel::message::MessageFlags;
+use twilight_model::channel::message::component::{Component, ActionRow, Button, ButtonStyle};
+use twilight_model::application::interaction::InteractionData;
+use twilight_model::http::interaction::{InteractionResponse, InteractionResponseType, InteractionResponseData};
+
+use vesper::macros::{command, check};
+use vesper::builder::FrameworkBuilder;
+use vesper::framework::{DefaultCommandResult, DefaultError};
+use vesper::context::SlashContext;
+
+use async_trait::async_trait;
+
+use tracing::{debug, info, error};
+
+use std::sync::Arc;
+
+use crate::{Context, all_members, guild_config, has_permission};
+use crate::config;
+use crate::ebas;
+use crate::module::Module;
+
+/// `/member verify` and `/member purge`, which give and take the member role based on eBas.
+pub struct MemberModule;
+
+#[async_trait]
+impl Module for MemberModule {
+	fn name(&self) -> &'static str {
+		"member"
+	}
+
+	fn validate(&self, guild: &config::Guild) -> Result<(), String> {
+		MemberConfig::new(guild).map(|_| ())
+	}
+
+	fn commands(&self, framework: FrameworkBuilder<Arc<Context>>) -> FrameworkBuilder<Arc<Context>> {
+		framework.group(|g| g
+			.name("member")
+			.description("Membership in the association")
+			.command(member_verify)
+			.command(member_purge))
+	}
+}
+
+/// The sections of the guild configuration that the module needs, which are optional
+/// since the module can be turned off.
+struct MemberConfig<'a> {
+	ebas: &'a config::Ebas,
+	member: &'a config::Member,
+}
+
+impl MemberConfig<'_> {
+	fn new(guild: &config::Guild) -> Result<MemberConfig<'_>, String> {
+		match (guild.ebas(), guild.member()) {
+			(Some(ebas), Some(member)) => Ok(MemberConfig { ebas, member }),
+			_ => Err(String::from("the member module needs ebas and member to be set, or to be turned off under [modules]")),
+		}
+	}
+}
+
+/// The member configuration of the guild, telling the user if the module isn't set up there.
+///
+/// The configuration is validated when it's read, so this only fails if it was changed
+/// without being validated.
+async fn member_config<'a>(ctx: &SlashContext<'_, Arc<Context>>, guild: &'a config::Guild) -> Option<MemberConfig<'a>> {
+	match MemberConfig::new(guild) {
+		Ok(config) => Some(config),
+		Err(reason) => {
+			error!(guild = %guild.id(), reason, "Member command was used without member configuration.");
+			let response = InteractionResponse {
+				kind: InteractionResponseType::ChannelMessageWithSource,
+				data: Some(InteractionResponseData {
+					content: Some(ctx.data.text(ctx.interaction.locale.as_deref(), "general.unconfigured_guild", &[])),
+					flags: Some(MessageFlags::EPHEMERAL),
+					..Default::default()
+				}),
+			};
+
+			let r = ctx.interaction_client.create_response(ctx.interaction.id, &ctx.interaction.token, &response).await;
+			if let Err(e) = r {
+				error!(error = %e, "Couldn't respond to command.");
+			}
+			None
+		},
+	}
+}
+
+#[check]
+async fn member_purge_permission(ctx: &SlashContext<Arc<Context>>) -> Result<bool, DefaultError> {
+	let config = match guild_config(ctx).await {
+		Some(config) => config,
+		None => return Ok(false),
+	};
+
+	let membership = match member_config(ctx, &config).await {
+		Some(membership) => membership,
+		None => return Ok(false),
+	};
+
+	Ok(has_permission(ctx, &config, membership.member.permission().purge()).await)
+}
+
+#[command(chat, name = "verify")]
+#[description = "Verify your membership"]
+async fn member_verify(
+	ctx: &mut SlashContext<Arc<Context>>,
+	#[description = "The email you used when registering"]
+	email: String
+) -> DefaultCommandResult {
+	let config = match guild_config(ctx).await {
+		Some(config) => config,
+		None => return Ok(()),
+	};
+
+	let membership = match member_config(ctx, &config).await {
+		Some(membership) => membership,
+		None => return Ok(()),
+	};
+
+	let locale = ctx.interaction.locale.clone();
+	let locale = locale.as_deref();
+
+	let is_member = ebas::verify_membership(Arc::clone(ctx.data), membership.ebas, email).await;
+	debug!(?is_member, "Verified membership.");
+
+	let response = match is_member {
+		Ok(true) => {
+			let user = match (&ctx.interaction.user, &ctx.interaction.member) {
+				(Some(user), _) => user,
+				(_, Some(member)) => match &member.user {
+					Some(user) => user,
+					None => panic!("User data in member should be set!"),
+				},
+				(None, None) => panic!("Either user or member should be set!"),
+			};
+
+			let guild = config.id();
+			let user = user.id;
+			let role = membership.member.role();
+
+			// NOTE This requires the MANAGE_ROLES permission when adding the bot to a guild.
+			info!(%user, %role, "Adding member role.");
+			ctx.http_client().add_guild_member_role(guild, user, role).await.expect("Couldn't add role to member.");
+
+			InteractionResponse {
+				kind: InteractionResponseType::ChannelMessageWithSource,
+				data: Some(InteractionResponseData {
+					content: Some(ctx.data.text(locale, "member.verified", &[("role", format!("<@&{}>", role))])),
+					flags: Some(MessageFlags::EPHEMERAL),
+					..Default::default()
+				}),
+			}
+		},
+		Ok(false) => InteractionResponse {
+			kind: InteractionResponseType::ChannelMessageWithSource,
+			data: Some(InteractionResponseData {
+				content: Some(ctx.data.text(locale, "member.not_found", &[])),
+				flags: Some(MessageFlags::EPHEMERAL),
+				..Default::default()
+			}),
+		},
+		Err(e) => {
+			error!(error = %e, "Couldn't verify membership with eBas.");
+			InteractionResponse {
+				kind: InteractionResponseType::ChannelMessageWithSource,
+				data: Some(InteractionResponseData {
+					content: Some(ctx.data.text(locale, "general.error", &[])),
+					flags: Some(MessageFlags::EPHEMERAL),
+					..Default::default()
+				}),
+			}
+		},
+	};
+
+	let r = ctx.interaction_client.create_response(ctx.interaction.id, &ctx.interaction.token, &response).await;
+	if let Err(e) = r {
+		error!(error = %e, "Couldn't respond to command.");
+	}
+
+	Ok(())
+}
+
+#[command(chat, name = "purge")]
+#[description = "Remove all users from the membership role"]
+#[checks(member_purge_permission)]
+async fn member_purge(ctx: &mut SlashContext<Arc<Context>>) -> DefaultCommandResult {
+	let id = ctx.interaction.id;
+
+	let config = match guild_config(ctx).await {
+		Some(config) => config,
+		None => return Ok(()),
+	};
+	let guild = config.id();
+	let role = match member_config(ctx, &config).await {
+		Some(membership) => membership.member.role(),
+		None => return Ok(()),
+	};
+	let locale = ctx.interaction.locale.clone();
+	let locale = locale.as_deref();
+
+	let response = InteractionResponse {
+		kind: InteractionResponseType::ChannelMessageWithSource,
+		data: Some(InteractionResponseData {
+			content: Some(ctx.data.text(locale, "member.purge_confirm", &[("role", format!("<@&{}>", role))])),
+			components: Some(
+				vec![Component::ActionRow(ActionRow {
+					components: vec![
+						Component::Button(Button {
+							custom_id: Some(format!("{}:member_purge_cancel", id)),
+							label: Some(ctx.data.text(locale, "member.cancel_button", &[])),
+							style: ButtonStyle::Secondary,
+							disabled: false,
+							emoji: None,
+							url: None,
+						}),
+						Component::Button(Button {
+							custom_id: Some(format!("{}:member_purge_confirm", id)),
+							label: Some(ctx.data.text(locale, "member.purge_button", &[])),
+							style: ButtonStyle::Danger,
+							disabled: false,
+							emoji: None,
+							url: None,
+						})],
+				})]),
+			flags: Some(MessageFlags::EPHEMERAL),
+			..Default::default()
+		}),
+	};
+
+	let r = ctx.interaction_client.create_response(ctx.interaction.id, &ctx.interaction.token, &response).await;
+	if let Err(e) = r {
+		error!(error = %e, "Couldn't respond to command.");
+	}
+
+	let interaction = ctx.wait_interaction(move |interaction| {
+		if let Some(InteractionData::MessageComponent(data)) = &interaction.data {
+			if data.custom_id.starts_with(&id.to_string()) {
+				return true;
+			}
+		}
+		false
+	}).await.expect("Error waiting for member purge response.");
+
+	let action = if let Some(InteractionData::MessageComponent(data)) = &interaction.data {
+		// SAFETY We know that the interaction starts with the id, so we can split at colon to get the action.
+		let (_, action) = data.custom_id.split_once(':').unwrap();
+		action
+	} else {
+		unreachable!()
+	};
+
+	match action {
+		"member_purge_confirm" => {
+			let response = ctx.data.text(locale, "member.purge_collecting", &[("role", format!("<@&{}>", role))]);
+
+			let r = ctx.interaction_client.update_response(&ctx.interaction.token)
+				.content(Some(&response)).expect("Response content was malformed.")
+				.components(None).expect("Components was malformed.")
+				.await;
+			if let Err(e) = r {
+				error!(error = %e, "Couldn't update response to command.");
+			}
+		},
+		"member_purge_cancel" => {
+			let r = ctx.interaction_client.update_response(&ctx.interaction.token)
+				.content(Some(&ctx.data.text(locale, "member.purge_aborted", &[]))).expect("Response content was malformed.")
+				.components(None).expect("Components was malformed.")
+				.await;
+			if let Err(e) = r {
+				error!(error = %e, "Couldn't update response to command.");
+			}
+			return Ok(());
+		},
+		_ => unreachable!(),
+	}
+
+	// Get all members in the guild that have the role.
+	info!(%role, "Collecting members for purge.");
+	let members: Vec<_> = match all_members(ctx.http_client(), guild).await {
+		Ok(members) => members.into_iter().filter(|member| member.roles.contains(&role)).collect(),
+		Err(e) => {
+			error!(error = %e, "Couldn't collect members for purge.");
+			let r = ctx.interaction_client.update_response(&ctx.interaction.token)
+				.content(Some(&ctx.data.text(locale, "general.error", &[]))).expect("Response content was malformed.")
+				.await;
+			if let Err(e) = r {
+				error!(error = %e, "Couldn't update response to command.");
+			}
+			return Ok(());
+		},
+	};
+
+	let content = ctx.data.text(locale, "member.purge_found", &[("count", members.len().to_string()), ("role", format!("<@&{}>", role))]);
+	let buttons = vec![Component::ActionRow(ActionRow {
+					components: vec![
+						Component::Button(Button {
+							custom_id: Some(format!("{}:member_purge_cancel", id)),
+							label: Some(ctx.data.text(locale, "member.cancel_button", &[])),
+							style: ButtonStyle::Secondary,
+							disabled: false,
+							emoji: None,
+							url: None,
+						}),
+						Component::Button(Button {
+							custom_id: Some(format!("{}:member_purge_confirm", id)),
+							label: Some(ctx.data.text(locale, "member.continue_button", &[])),
+							style: ButtonStyle::Danger,
+							disabled: false,
+							emoji: None,
+							url: None,
+						})],
+				})];
+	let message = ctx.interaction_client.create_followup(&ctx.interaction.token)
+		.content(&content).expect("Response content was malformed.")
+		.components(&buttons).expect("Components was malformed.")
+		.flags(MessageFlags::EPHEMERAL)
+		.await.expect("Something went wrong when responding to command.")
+		.model().await.expect("Couldn't deserialize message.");
+	let confirmation_message_id = message.id;
+
+	let interaction = ctx.wait_interaction(move |interaction| {
+		if let Some(InteractionData::MessageComponent(data)) = &interaction.data {
+			if data.custom_id.starts_with(&id.to_string()) {
+				return true;
+			}
+		}
+		false
+	}).await.expect("Error waiting for member purge response.");
+
+	let action = if let Some(InteractionData::MessageComponent(data)) = &interaction.data {
+		// SAFETY We know that the interaction starts with the id, so we can split at colon to get the action.
+		let (_, action) = data.custom_id.split_once(':').unwrap();
+		action
+	} else {
+		unreachable!()
+	};
+
+	match action {
+		"member_purge_confirm" => {
+			let r = ctx.interaction_client.update_followup(&ctx.interaction.token, confirmation_message_id)
+				.content(Some(&ctx.data.text(locale, "member.purge_starting", &[("count", members.len().to_string()), ("role", format!("<@&{}>", role))]))).expect("Response content was malformed.")
+				.components(None).expect("Components was malformed.")
+				.await;
+			if let Err(e) = r {
+				error!(error = %e, "Couldn't update response to command.");
+			}
+		},
+		"member_purge_cancel" => {
+			let r = ctx.interaction_client.update_followup(&ctx.interaction.token, confirmation_message_id)
+				.content(Some(&ctx.data.text(locale, "member.purge_cancelled", &[]))).expect("Response content was malformed.")
+				.components(None).expect("Components was malformed.")
+				.await;
+			if let Err(e) = r {
+				error!(error = %e, "Couldn't update response to command.");
+			}
+			return Ok(());
+		},
+		_ => unreachable!(),
+	}
+
+	// Remove the role from each member.
+	info!(count = members.len(), %role, "Purging members.");
+	ctx.data.metrics.set_purge_remaining(members.len());
+	for member in members {
+		let user = member.user.id;
+		// NOTE This requires the MANAGE_ROLES permission when adding the bot to a guild.
+		debug!(%user, %role, "Removing member role.");
+		ctx.http_client()
+			.remove_guild_member_role(guild, user, role)
+			.await.expect("Couldn't remove role from member.");
+		ctx.data.metrics.purge_removed();
+	}
+	info!(%role, "Purge finished.");
+
+	// Say that we are done with the purge.
+	let r = ctx.interaction_client.create_followup(&ctx.interaction.token)
+		.content(&ctx.data.text(locale, "member.purge_done", &[("role", format!("<@&{}>", role))])).expect("Response content was malformed.")
+		.flags(MessageFlags::EPHEMERAL)
+		.await;
+	if let Err(e) = r {
+		error!(error = %e, "Couldn't send followup to command.");
+	}
+
+	Ok(())
+}
@@ -0,0 +1,97 @@
+use prometheus::{Registry, Encoder, TextEncoder, Histogram, HistogramOpts, IntCounter, IntCounterVec, IntGauge, Opts};
+
+pub struct Metrics {
+	registry: Registry,
+	commands: IntCounterVec,
+	ebas_duration: Histogram,
+	ebas_errors: IntCounterVec,
+	purge_remaining: IntGauge,
+	purge_removed: IntCounter,
+	gateway_reconnects: IntCounter,
+}
+
+impl Metrics {
+	pub fn new() -> Metrics {
+		let registry = Registry::new_custom(Some(String::from("kodbot")), None)
+			.expect("Metric prefix is valid.");
+
+		let commands = IntCounterVec::new(
+			Opts::new("commands_total", "Number of handled commands by outcome."),
+			&["command", "outcome"],
+		).expect("Metric is valid.");
+		let ebas_duration = Histogram::with_opts(
+			HistogramOpts::new("ebas_request_duration_seconds", "Time taken by requests to eBas."),
+		).expect("Metric is valid.");
+		let ebas_errors = IntCounterVec::new(
+			Opts::new("ebas_errors_total", "Number of failed requests to eBas by kind of failure."),
+			&["kind"],
+		).expect("Metric is valid.");
+		let purge_remaining = IntGauge::new(
+			"purge_remaining_members",
+			"Number of members that remain to be removed by a running purge.",
+		).expect("Metric is valid.");
+		let purge_removed = IntCounter::new(
+			"purge_removed_members_total",
+			"Number of members removed from the member role by purges.",
+		).expect("Metric is valid.");
+		let gateway_reconnects = IntCounter::new(
+			"gateway_reconnects_total",
+			"Number of times the gateway connection has been reestablished.",
+		).expect("Metric is valid.");
+
+		registry.register(Box::new(commands.clone())).expect("Metric is only registered once.");
+		registry.register(Box::new(ebas_duration.clone())).expect("Metric is only registered once.");
+		registry.register(Box::new(ebas_errors.clone())).expect("Metric is only registered once.");
+		registry.register(Box::new(purge_remaining.clone())).expect("Metric is only registered once.");
+		registry.register(Box::new(purge_removed.clone())).expect("Metric is only registered once.");
+		registry.register(Box::new(gateway_reconnects.clone())).expect("Metric is only registered once.");
+
+		Metrics {
+			registry,
+			commands,
+			ebas_duration,
+			ebas_errors,
+			purge_remaining,
+			purge_removed,
+			gateway_reconnects,
+		}
+	}
+
+	pub fn command(&self, command: &str, outcome: &str) {
+		self.commands.with_label_values(&[command, outcome]).inc();
+	}
+
+	pub fn ebas_duration(&self) -> &Histogram {
+		&self.ebas_duration
+	}
+
+	pub fn ebas_error(&self, kind: &str) {
+		self.ebas_errors.with_label_values(&[kind]).inc();
+	}
+
+	// Purges are only run by the member module, which may be left out of the build.
+	#[cfg_attr(not(feature = "member"), allow(dead_code))]
+	pub fn set_purge_remaining(&self, remaining: usize) {
+		self.purge_remaining.set(remaining as i64);
+	}
+
+	#[cfg_attr(not(feature = "member"), allow(dead_code))]
+	pub fn purge_removed(&self) {
+		self.purge_removed.inc();
+		self.purge_remaining.dec();
+	}
+
+	pub fn gateway_reconnected(&self) {
+		self.gateway_reconnects.inc();
+	}
+
+	/// Renders all metrics in the Prometheus text format.
+	pub fn encode(&self) -> String {
+		let mut buffer = Vec::new();
+		TextEncoder::new()
+			.encode(&self.registry.gather(), &mut buffer)
+			.expect("Metrics can be encoded.");
+		// SAFETY The text encoder only produces UTF-8.
+		String::from_utf8(buffer).unwrap()
+	}
+}
@@ -0,0 +1,77 @@
+use twilight_http::Client;
+use twilight_gateway::{Event, Intents};
+use twilight_model::application::interaction::Interaction;
+
+use vesper::builder::FrameworkBuilder;
+use vesper::framework::Framework;
+
+use async_trait::async_trait;
+
+use std::sync::Arc;
+
+use crate::Context;
+use crate::config;
+
+/// A feature of the bot, such as the membership commands or the welcome message.
+///
+/// Modules are turned off under `[modules]` in the configuration. Some can also be left
+/// out of the build with the cargo feature of the same name.
+#[async_trait]
+pub trait Module: Send + Sync {
+	/// The name of the module under `[modules]`.
+	fn name(&self) -> &'static str;
+
+	/// The gateway intents that the events handled by [`Module::event`] need.
+	fn intents(&self) -> Intents {
+		Intents::empty()
+	}
+
+	/// Checks that a guild has the parts of the configuration that the module needs.
+	fn validate(&self, _guild: &config::Guild) -> Result<(), String> {
+		Ok(())
+	}
+
+	/// Adds the commands of the module to the framework.
+	fn commands(&self, framework: FrameworkBuilder<Arc<Context>>) -> FrameworkBuilder<Arc<Context>> {
+		framework
+	}
+
+	/// Runs for each guild when the bot starts and when the configuration is reloaded.
+	async fn sync(&self, _client: &Client, _context: &Arc<Context>, _guild: &config::Guild) {}
+
+	/// Whether clicks on the component with `custom_id` are handled by [`Module::component`].
+	fn handles_component(&self, _custom_id: &str) -> bool {
+		false
+	}
+
+	async fn component(&self, _framework: &Framework<Arc<Context>>, _interaction: Interaction) {}
+
+	/// Handles an event from the gateway other than an interaction.
+	async fn event(&self, _framework: &Framework<Arc<Context>>, _event: &Event) {}
+}
+
+/// The names of all modules, including the ones left out of this build.
+pub const NAMES: &[&str] = &["member", "welcome", "roles", "rules", "sticky", "join"];
+
+/// The modules in this build.
+static MODULES: &[&dyn Module] = &[
+	#[cfg(feature = "member")]
+	&crate::member::MemberModule,
+	&crate::welcome::WelcomeModule,
+	&crate::roles::RolesModule,
+	&crate::rules::RulesModule,
+	#[cfg(feature = "sticky")]
+	&crate::sticky::StickyModule,
+	#[cfg(feature = "join")]
+	&crate::join::JoinModule,
+];
+
+/// The gateway intents that the enabled modules need.
+pub fn intents(config: &config::Config) -> Intents {
+	enabled(config).fold(Intents::empty(), |intents, module| intents | module.intents())
+}
+
+/// The modules in this build that are enabled in the configuration.
+pub fn enabled(config: &config::Config) -> impl Iterator<Item = &'static dyn Module> + '_ {
+	MODULES.iter().copied().filter(|m| config.modules().enabled(m.name()))
+}
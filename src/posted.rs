@@ -0,0 +1,260 @@
+use twilight_http::Client;
+use twilight_http::api_error::ApiError;
+use twilight_http::error::ErrorType;
+use twilight_model::id::{Id, marker::{ChannelMarker, MessageMarker}};
+use twilight_model::channel::Message;
+
+use tracing::{debug, info, warn, error};
+
+use std::path::PathBuf;
+
+use crate::embed::{self, LoadedEmbed};
+use crate::state::StateError;
+use crate::template::{Resolver, TemplateError};
+use crate::welcome::Post;
+
+/// The JSON error code Discord answers with when a message doesn't exist.
+const UNKNOWN_MESSAGE: u64 = 10008;
+
+/// How a posted message compares to what should be posted.
+pub enum ValidationError {
+	MessageNotFound,
+	WrongContent,
+	Other(Box<twilight_http::Error>),
+}
+
+#[derive(Debug)]
+pub enum SyncError {
+	/// The message, named by its kind, isn't configured or has nothing to post.
+	NotConfigured(String),
+	Embeds(embed::EmbedError),
+	Template(TemplateError),
+	State(StateError),
+	Http(Box<twilight_http::Error>),
+}
+
+impl std::fmt::Display for SyncError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			SyncError::NotConfigured(kind) => write!(f, "{} isn't configured or is empty", kind),
+			SyncError::Embeds(e) => write!(f, "couldn't read embeds: {}", e),
+			SyncError::Template(e) => write!(f, "{}", e),
+			SyncError::State(e) => write!(f, "{}", e),
+			SyncError::Http(e) => write!(f, "couldn't update the messages in Discord: {}", e),
+		}
+	}
+}
+
+/// Reads the text and embeds of a welcome message, a translation of it or a sticky message,
+/// with the placeholders for the guild filled in.
+pub async fn load(resolver: &Resolver<'_>, content: Option<String>, embeds: Option<PathBuf>) -> Result<(String, Vec<LoadedEmbed>), SyncError> {
+	let embeds = match embeds {
+		Some(f) => {
+			let s = embed::read(&f).map_err(SyncError::Embeds)?;
+			let s = resolver.render(&s).await.map_err(SyncError::Template)?;
+			embed::from_str(&f, &s).map_err(SyncError::Embeds)?
+		},
+		None => Vec::new(),
+	};
+
+	let content = resolver.render(&content.unwrap_or_default()).await.map_err(SyncError::Template)?;
+
+	Ok((content, embeds))
+}
+
+// NOTE This requires the SEND_MESSAGES and ATTACH_FILES permissions.
+pub async fn post_message(client: &Client, kind: &str, channel: Id<ChannelMarker>, post: &Post) -> Result<Message, SyncError> {
+	info!(kind, %channel, "Posting message.");
+	let embeds = post.embeds();
+	let attachments = embed::attachments(&post.files()).map_err(SyncError::Embeds)?;
+
+	let mut request = client
+		.create_message(channel)
+		.embeds(&embeds).expect("Message was malformed.")
+		.components(post.components()).expect("Message was malformed.")
+		.attachments(&attachments).expect("Message was malformed.");
+	if !post.content().is_empty() {
+		request = request.content(post.content()).expect("Message was malformed.");
+	}
+
+	Ok(request
+		.await.map_err(|e| SyncError::Http(Box::new(e)))?
+		.model().await.expect("Couldn't deserialize message from response."))
+}
+
+// NOTE This doesn't require any permissions, since we only edit our own messages.
+pub async fn edit_message(client: &Client, kind: &str, channel: Id<ChannelMarker>, message: Id<MessageMarker>, post: &Post) -> Result<(), SyncError> {
+	info!(kind, %channel, %message, "Editing message.");
+	let embeds = post.embeds();
+	// Attachments that aren't uploaded again are removed, so old versions of the files don't pile up.
+	let attachments = embed::attachments(&post.files()).map_err(SyncError::Embeds)?;
+	let content = Some(post.content()).filter(|c| !c.is_empty());
+
+	client
+		.update_message(channel, message)
+		.content(content).expect("Message was malformed.")
+		.embeds(Some(&embeds)).expect("Message was malformed.")
+		.components(Some(post.components())).expect("Message was malformed.")
+		.attachments(&attachments).expect("Message was malformed.")
+		.await.map_err(|e| SyncError::Http(Box::new(e)))?;
+
+	Ok(())
+}
+
+// NOTE This doesn't require any permissions, since we only delete our own messages.
+pub async fn delete_message(client: &Client, kind: &str, channel: Id<ChannelMarker>, message: Id<MessageMarker>) -> Result<(), SyncError> {
+	info!(kind, %channel, %message, "Deleting message.");
+	match client.delete_message(channel, message).await {
+		Ok(_) => Ok(()),
+		// The message may already have been deleted by someone else, which is fine.
+		Err(e) if is_unknown_message(&e) => {
+			warn!(kind, %channel, %message, "Message was already deleted.");
+			Ok(())
+		},
+		Err(e) => Err(SyncError::Http(Box::new(e))),
+	}
+}
+
+/// Whether Discord answered that the message doesn't exist, as opposed to any other error.
+fn is_unknown_message(error: &twilight_http::Error) -> bool {
+	match error.kind() {
+		ErrorType::Response { status, error, .. } => status.get() == 404
+			|| matches!(error, ApiError::General(e) if e.code == UNKNOWN_MESSAGE),
+		_ => false,
+	}
+}
+
+pub async fn validate_message(
+	client: &Client,
+	kind: &str,
+	channel: Id<ChannelMarker>,
+	message: Id<MessageMarker>,
+	post: &Post,
+) -> Result<(), ValidationError> {
+	debug!(kind, %channel, %message, "Fetching message.");
+	let response = match client.message(channel, message).await {
+		Ok(response) => response,
+		Err(e) if is_unknown_message(&e) => {
+			warn!(kind, %channel, %message, "Message is gone.");
+			return Err(ValidationError::MessageNotFound);
+		},
+		Err(e) => {
+			error!(kind, %channel, %message, error = %e, "Couldn't fetch message.");
+			return Err(ValidationError::Other(Box::new(e)));
+		},
+	};
+
+	let message = response.model().await
+		.expect("Couldn't deserialize message from response.");
+
+	if !post.matches(&message) {
+		return Err(ValidationError::WrongContent);
+	}
+
+	Ok(())
+}
+
+/// What syncing a message did to the messages in the channel.
+#[derive(Default)]
+pub struct Changes {
+	messages: Vec<Id<MessageMarker>>,
+	unchanged: usize,
+	edited: usize,
+	posted: usize,
+	deleted: usize,
+}
+
+impl Changes {
+	/// The messages that make up the message afterwards.
+	pub fn messages(&self) -> &[Id<MessageMarker>] {
+		&self.messages
+	}
+
+	pub fn unchanged(&self) -> usize {
+		self.unchanged
+	}
+
+	pub fn edited(&self) -> usize {
+		self.edited
+	}
+
+	pub fn posted(&self) -> usize {
+		self.posted
+	}
+
+	pub fn deleted(&self) -> usize {
+		self.deleted
+	}
+
+	/// Counts a message that was deleted after the sync, like the old messages of a repost.
+	pub(crate) fn add_deleted(&mut self) {
+		self.deleted += 1;
+	}
+}
+
+/// Makes the posted messages match `posts`, reusing the messages in `posted` where possible.
+/// `kind` names the message in the logs, e.g. "welcome message".
+///
+/// Messages are edited in place as long as they still exist. If one of them is gone, it
+/// and every message after it are reposted, since new messages can only be added at the
+/// end of the channel. If posting fails, the messages posted so far are deleted again, so
+/// that the next sync doesn't leave them behind.
+pub async fn sync_messages(
+	client: &Client,
+	kind: &str,
+	channel: Id<ChannelMarker>,
+	posted: &[Id<MessageMarker>],
+	posts: &[Post],
+) -> Result<Changes, SyncError> {
+	let mut changes = Changes {
+		messages: Vec::with_capacity(posts.len()),
+		..Default::default()
+	};
+	let mut kept = 0;
+	// The message that was found to be gone doesn't have to be deleted.
+	let mut gone = 0;
+
+	for (&message, post) in posted.iter().zip(posts) {
+		match validate_message(client, kind, channel, message, post).await {
+			Ok(_) => {
+				debug!(kind, %message, "Message is up to date.");
+				changes.unchanged += 1;
+			},
+			Err(ValidationError::WrongContent) => {
+				edit_message(client, kind, channel, message, post).await?;
+				changes.edited += 1;
+			},
+			Err(ValidationError::MessageNotFound) => {
+				gone = 1;
+				break;
+			},
+			// Anything else, like a missing permission or Discord being down, doesn't mean
+			// that the message is gone, so reposting it would leave a duplicate behind.
+			Err(ValidationError::Other(e)) => return Err(SyncError::Http(e)),
+		}
+		changes.messages.push(message);
+		kept += 1;
+	}
+
+	for &message in &posted[kept + gone..] {
+		delete_message(client, kind, channel, message).await?;
+		changes.deleted += 1;
+	}
+
+	for post in &posts[kept..] {
+		match post_message(client, kind, channel, post).await {
+			Ok(message) => changes.messages.push(message.id),
+			Err(e) => {
+				for &message in &changes.messages[kept..] {
+					if let Err(e) = delete_message(client, kind, channel, message).await {
+						error!(kind, %channel, %message, error = %e, "Couldn't delete partly posted message!");
+					}
+				}
+				return Err(e);
+			},
+		}
+		changes.posted += 1;
+	}
+
+	Ok(changes)
+}
@@ -0,0 +1,215 @@
+use twilight_http::Client;
+
+use notify::{Watcher, RecommendedWatcher, RecursiveMode};
+
+use tracing::{debug, info, warn, error};
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::Context;
+use crate::config;
+use crate::module;
+
+/// Editors tend to write a file in several steps, so wait for changes to settle before reloading.
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Watches the configuration and the files of the welcome and sticky messages, and reloads them when they change
+/// or when the process receives SIGHUP.
+pub async fn watch(config_path: PathBuf, client: Arc<Client>, context: Arc<Context>) {
+	let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+	let mut watcher = match notify::recommended_watcher(move |event| {
+		let _ = tx.send(event);
+	}) {
+		Ok(watcher) => Some(watcher),
+		Err(e) => {
+			warn!(error = %e, "Couldn't watch configuration files, only SIGHUP will reload them.");
+			None
+		},
+	};
+
+	let mut files = HashSet::new();
+	let mut directories = HashSet::new();
+	if let Some(watcher) = &mut watcher {
+		update_watches(watcher, &config_path, &context.config(), &mut files, &mut directories);
+	}
+
+	let mut hangup = Hangup::new();
+
+	loop {
+		tokio::select! {
+			Some(event) = rx.recv() => {
+				let event: notify::Event = match event {
+					Ok(event) => event,
+					Err(e) => {
+						warn!(error = %e, "Error while watching configuration files.");
+						continue;
+					},
+				};
+
+				if event.kind.is_access() || !event.paths.iter().any(|p| files.contains(&normalize(p))) {
+					continue;
+				}
+
+				debug!(paths = ?event.paths, "Configuration files changed.");
+				tokio::time::sleep(DEBOUNCE).await;
+				while rx.try_recv().is_ok() {}
+			},
+			_ = hangup.recv() => info!("Received SIGHUP."),
+		}
+
+		reload(&config_path, &client, &context).await;
+
+		if let Some(watcher) = &mut watcher {
+			update_watches(watcher, &config_path, &context.config(), &mut files, &mut directories);
+		}
+	}
+}
+
+/// Reads the configuration again and swaps it into the context if it's valid.
+pub async fn reload(config_path: &Path, client: &Client, context: &Arc<Context>) {
+	let config = match config::from_file(config_path) {
+		Ok(config) => config,
+		Err(e) => {
+			error!(error = %e, "Not reloading invalid configuration.");
+			return;
+		},
+	};
+
+	// Commands are registered in the guilds when starting, so the bot has to restart to change them.
+	let guilds = |config: &config::Config| config.guilds().iter().map(|g| g.id()).collect::<HashSet<_>>();
+	if guilds(&config) != guilds(&context.config()) {
+		error!("Not reloading configuration, changing the guilds requires a restart.");
+		return;
+	}
+
+	// The framework is built with the commands of the enabled modules when starting.
+	let modules = |config: &config::Config| module::enabled(config).map(|m| m.name()).collect::<Vec<_>>();
+	if modules(&config) != modules(&context.config()) {
+		error!("Not reloading configuration, turning modules on or off requires a restart.");
+		return;
+	}
+
+	// The shards are created when starting, while the backoff is read every time it's needed.
+	if config.gateway().shards() != context.config().gateway().shards() {
+		error!("Not reloading configuration, changing the number of shards requires a restart.");
+		return;
+	}
+
+	// The credentials for eBas are read with the secrets when starting.
+	if let Some(association) = config.associations().iter().find(|a| context.secrets.ebas(a.as_deref()).is_none()) {
+		let association = association.as_deref().unwrap_or("default");
+		error!(association, "Not reloading configuration, adding an eBas association requires a restart.");
+		return;
+	}
+
+	context.set_config(config);
+	info!(path = %config_path.display(), "Reloaded configuration.");
+
+	let config = context.config();
+	for guild in config.guilds() {
+		for module in module::enabled(&config) {
+			module.sync(client, context, guild).await;
+		}
+	}
+}
+
+/// Watches the directories of the files rather than the files themselves, since editors
+/// often replace a file instead of writing to it, which would end a watch on the file.
+fn update_watches(
+	watcher: &mut RecommendedWatcher,
+	config_path: &Path,
+	config: &config::Config,
+	files: &mut HashSet<PathBuf>,
+	directories: &mut HashSet<PathBuf>,
+) {
+	files.clear();
+	files.insert(normalize(config_path));
+	for welcome in config.guilds().iter().filter_map(|g| g.welcome()) {
+		for file in welcome.source().file().into_iter().chain(welcome.source().embeds()) {
+			files.insert(normalize(&file));
+		}
+	}
+	for sticky in config.guilds().iter().flat_map(|g| g.sticky()) {
+		for file in sticky.source().file().into_iter().chain(sticky.source().embeds()) {
+			files.insert(normalize(&file));
+		}
+	}
+
+	let wanted: HashSet<PathBuf> = files.iter()
+		.filter_map(|f| f.parent().map(Path::to_path_buf))
+		.collect();
+
+	for directory in directories.difference(&wanted) {
+		if let Err(e) = watcher.unwatch(directory) {
+			debug!(directory = %directory.display(), error = %e, "Couldn't stop watching directory.");
+		}
+	}
+
+	for directory in wanted.difference(directories) {
+		if let Err(e) = watcher.watch(directory, RecursiveMode::NonRecursive) {
+			warn!(directory = %directory.display(), error = %e, "Couldn't watch directory.");
+		}
+	}
+
+	*directories = wanted;
+}
+
+/// Makes a path absolute without requiring the file to exist, since it may be
+/// in the middle of being replaced.
+fn normalize(path: &Path) -> PathBuf {
+	let (directory, name) = match (path.parent(), path.file_name()) {
+		(Some(directory), Some(name)) => (directory, name),
+		_ => return path.to_path_buf(),
+	};
+
+	let directory = if directory.as_os_str().is_empty() {
+		Path::new(".")
+	} else {
+		directory
+	};
+
+	match directory.canonicalize() {
+		Ok(directory) => directory.join(name),
+		Err(_) => path.to_path_buf(),
+	}
+}
+
+struct Hangup {
+	#[cfg(unix)]
+	signal: Option<tokio::signal::unix::Signal>,
+}
+
+impl Hangup {
+	fn new() -> Hangup {
+		#[cfg(unix)]
+		{
+			use tokio::signal::unix::{signal, SignalKind};
+
+			let signal = match signal(SignalKind::hangup()) {
+				Ok(signal) => Some(signal),
+				Err(e) => {
+					warn!(error = %e, "Couldn't install SIGHUP handler.");
+					None
+				},
+			};
+
+			Hangup { signal }
+		}
+
+		#[cfg(not(unix))]
+		Hangup {}
+	}
+
+	async fn recv(&mut self) {
+		#[cfg(unix)]
+		if let Some(signal) = &mut self.signal {
+			signal.recv().await;
+			return;
+		}
+
+		std::future::pending::<()>().await
+	}
+}
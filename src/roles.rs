@@ -0,0 +1,234 @@
+use twilight_http::Client;
+use twilight_model::application::interaction::{Interaction, InteractionData};
+use twilight_model::channel::message::{MessageFlags, ReactionType};
+use twilight_model::channel::message::component::{Component, ActionRow, Button, ButtonStyle, SelectMenu, SelectMenuOption};
+use twilight_model::http::interaction::{InteractionResponse, InteractionResponseType, InteractionResponseData};
+use twilight_model::id::Id;
+use twilight_model::id::marker::{ApplicationMarker, RoleMarker};
+
+use vesper::framework::Framework;
+
+use async_trait::async_trait;
+
+use tracing::{info, warn, error};
+
+use std::sync::Arc;
+
+use crate::Context;
+use crate::config::{RoleMenu, RoleMenuStyle};
+use crate::module::Module;
+
+/// The start of the custom id of every role menu component.
+const PREFIX: &str = "roles:";
+
+/// The menus for picking roles under the welcome and sticky messages.
+pub struct RolesModule;
+
+#[async_trait]
+impl Module for RolesModule {
+	fn name(&self) -> &'static str {
+		"roles"
+	}
+
+	fn handles_component(&self, custom_id: &str) -> bool {
+		is_role_menu(custom_id)
+	}
+
+	async fn component(&self, framework: &Framework<Arc<Context>>, interaction: Interaction) {
+		handle(framework.http_client(), framework.application_id, Arc::clone(&framework.data), interaction).await;
+	}
+}
+
+/// Whether the custom id of a component belongs to a role menu.
+pub fn is_role_menu(custom_id: &str) -> bool {
+	custom_id.starts_with(PREFIX)
+}
+
+/// Builds the rows of components for the role menus.
+///
+/// Select menus have the custom id `roles:<menu>`, buttons `roles:<menu>:<role>`.
+pub fn components(menus: &[RoleMenu]) -> Vec<Component> {
+	let mut rows = Vec::new();
+
+	for menu in menus {
+		match menu.style() {
+			RoleMenuStyle::Select => {
+				let options = menu.roles().iter().map(|o| SelectMenuOption {
+					default: false,
+					description: o.description().map(String::from),
+					emoji: o.emoji().map(emoji),
+					label: String::from(o.label()),
+					value: o.role().to_string(),
+				}).collect();
+
+				rows.push(Component::ActionRow(ActionRow {
+					components: vec![Component::SelectMenu(SelectMenu {
+						custom_id: format!("{}{}", PREFIX, menu.name()),
+						disabled: false,
+						// Choosing nothing removes all roles of the menu.
+						min_values: Some(0),
+						max_values: Some(menu.max() as u8),
+						options,
+						placeholder: menu.placeholder().map(String::from),
+					})],
+				}));
+			},
+			RoleMenuStyle::Buttons => {
+				for chunk in menu.roles().chunks(5) {
+					let buttons = chunk.iter().map(|o| Component::Button(Button {
+						custom_id: Some(format!("{}{}:{}", PREFIX, menu.name(), o.role())),
+						disabled: false,
+						emoji: o.emoji().map(emoji),
+						label: Some(String::from(o.label())),
+						style: ButtonStyle::Secondary,
+						url: None,
+					})).collect();
+
+					rows.push(Component::ActionRow(ActionRow {
+						components: buttons,
+					}));
+				}
+			},
+		}
+	}
+
+	rows
+}
+
+/// Parses a custom emoji such as `<:ac:519296526644936705>`, or else takes it as a unicode emoji.
+fn emoji(s: &str) -> ReactionType {
+	let custom = s.strip_prefix('<').and_then(|s| s.strip_suffix('>')).and_then(|s| {
+		let (animated, s) = match s.strip_prefix('a') {
+			Some(s) => (true, s),
+			None => (false, s),
+		};
+		let (name, id) = s.strip_prefix(':')?.split_once(':')?;
+		Some(ReactionType::Custom {
+			animated,
+			id: id.parse().ok()?,
+			name: Some(String::from(name)),
+		})
+	});
+
+	custom.unwrap_or_else(|| ReactionType::Unicode { name: String::from(s) })
+}
+
+/// Gives or takes the roles that a member chose in a role menu.
+pub async fn handle(client: &Client, application: Id<ApplicationMarker>, context: Arc<Context>, interaction: Interaction) {
+	let content = match update_roles(client, &context, &interaction).await {
+		Ok(content) => {
+			context.metrics.command("roles", "success");
+			content
+		},
+		Err(content) => {
+			context.metrics.command("roles", "error");
+			content
+		},
+	};
+
+	let response = InteractionResponse {
+		kind: InteractionResponseType::ChannelMessageWithSource,
+		data: Some(InteractionResponseData {
+			content: Some(content),
+			flags: Some(MessageFlags::EPHEMERAL),
+			..Default::default()
+		}),
+	};
+
+	let r = client.interaction(application).create_response(interaction.id, &interaction.token, &response).await;
+	if let Err(e) = r {
+		error!(error = %e, "Couldn't respond to role menu.");
+	}
+}
+
+/// Returns the message to show the member, as an error if nothing could be done.
+async fn update_roles(client: &Client, context: &Context, interaction: &Interaction) -> Result<String, String> {
+	let text = |key, placeholders: &[(&str, String)]| context.text(interaction.locale.as_deref(), key, placeholders);
+
+	let data = match &interaction.data {
+		Some(InteractionData::MessageComponent(data)) => data,
+		_ => return Err(text("general.error", &[])),
+	};
+
+	let config = interaction.guild_id.and_then(|guild| context.config().guild(guild));
+	let (config, member) = match (config, &interaction.member) {
+		(Some(config), Some(member)) => (config, member),
+		_ => return Err(text("general.unconfigured_guild", &[])),
+	};
+	let user = match &member.user {
+		Some(user) => user.id,
+		None => panic!("User data in member should be set!"),
+	};
+
+	// SAFETY The handler is only called for custom ids with the prefix.
+	let id = data.custom_id.strip_prefix(PREFIX).unwrap();
+	let (name, button) = match id.split_once(':') {
+		Some((name, role)) => (name, Some(role)),
+		None => (id, None),
+	};
+
+	let menu = match config.role_menu(name) {
+		Some(menu) => menu,
+		None => {
+			warn!(menu = name, "Role menu is no longer configured.");
+			return Err(text("roles.menu_gone", &[]));
+		},
+	};
+
+	let current: Vec<Id<RoleMarker>> = member.roles.iter()
+		.copied()
+		.filter(|r| menu.role(*r).is_some())
+		.collect();
+
+	let wanted: Vec<Id<RoleMarker>> = match button {
+		Some(role) => {
+			let role = match role.parse().ok().filter(|r| menu.role(*r).is_some()) {
+				Some(role) => role,
+				None => return Err(text("roles.role_gone", &[])),
+			};
+
+			if current.contains(&role) {
+				current.iter().copied().filter(|r| *r != role).collect()
+			} else if menu.max() == 1 {
+				vec![role]
+			} else if current.len() >= menu.max() {
+				return Err(text("roles.too_many", &[("max", menu.max().to_string())]));
+			} else {
+				current.iter().copied().chain(Some(role)).collect()
+			}
+		},
+		None => data.values.iter()
+			.filter_map(|v| v.parse().ok())
+			.filter(|r| menu.role(*r).is_some())
+			.take(menu.max())
+			.collect(),
+	};
+
+	let added: Vec<_> = wanted.iter().filter(|r| !current.contains(r)).copied().collect();
+	let removed: Vec<_> = current.iter().filter(|r| !wanted.contains(r)).copied().collect();
+
+	let guild = config.id();
+	// NOTE This requires the MANAGE_ROLES permission, and the roles have to be below the bot's highest role.
+	for &role in &added {
+		info!(%user, %role, menu = name, "Adding role from menu.");
+		if let Err(e) = client.add_guild_member_role(guild, user, role).await {
+			error!(%user, %role, error = %e, "Couldn't add role to member.");
+			return Err(text("roles.add_failed", &[]));
+		}
+	}
+	for &role in &removed {
+		info!(%user, %role, menu = name, "Removing role from menu.");
+		if let Err(e) = client.remove_guild_member_role(guild, user, role).await {
+			error!(%user, %role, error = %e, "Couldn't remove role from member.");
+			return Err(text("roles.remove_failed", &[]));
+		}
+	}
+
+	let mention = |roles: &[Id<RoleMarker>]| roles.iter().map(|r| format!("<@&{}>", r)).collect::<Vec<_>>().join(", ");
+	Ok(match (added.is_empty(), removed.is_empty()) {
+		(true, true) => text("roles.unchanged", &[]),
+		(false, true) => text("roles.added", &[("added", mention(&added))]),
+		(true, false) => text("roles.removed", &[("removed", mention(&removed))]),
+		(false, false) => text("roles.changed", &[("added", mention(&added)), ("removed", mention(&removed))]),
+	})
+}
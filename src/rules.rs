@@ -0,0 +1,260 @@
+use twilight_http::Client;
+use twilight_model::application::interaction::Interaction;
+use twilight_model::channel::message::MessageFlags;
+use twilight_model::channel::message::component::{Component, ActionRow, Button, ButtonStyle};
+use twilight_model::http::interaction::{InteractionResponse, InteractionResponseType, InteractionResponseData};
+use twilight_model::id::Id;
+use twilight_model::id::marker::ApplicationMarker;
+
+use vesper::macros::{command, check};
+use vesper::builder::FrameworkBuilder;
+use vesper::framework::{Framework, DefaultCommandResult, DefaultError};
+use vesper::context::SlashContext;
+
+use async_trait::async_trait;
+
+use sha2::{Sha256, Digest};
+
+use tracing::{info, warn, error};
+
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{Context, MembersError, all_members, guild_config, has_permission};
+use crate::config;
+use crate::module::Module;
+use crate::state::Acceptance;
+
+/// The custom id of the button for accepting the rules.
+pub const ACCEPT: &str = "rules:accept";
+
+/// The button for accepting the rules under the welcome message, and `/rules reset`.
+pub struct RulesModule;
+
+#[async_trait]
+impl Module for RulesModule {
+	fn name(&self) -> &'static str {
+		"rules"
+	}
+
+	fn commands(&self, framework: FrameworkBuilder<Arc<Context>>) -> FrameworkBuilder<Arc<Context>> {
+		framework.group(|g| g
+			.name("rules")
+			.description("The rules of the server")
+			.command(rules_reset))
+	}
+
+	fn handles_component(&self, custom_id: &str) -> bool {
+		custom_id == ACCEPT
+	}
+
+	async fn component(&self, framework: &Framework<Arc<Context>>, interaction: Interaction) {
+		handle(framework.http_client(), framework.application_id, Arc::clone(&framework.data), interaction).await;
+	}
+}
+
+/// Builds the row with the button for accepting the rules.
+pub fn components(rules: &config::Rules) -> Component {
+	Component::ActionRow(ActionRow {
+		components: vec![Component::Button(Button {
+			custom_id: Some(String::from(ACCEPT)),
+			disabled: false,
+			emoji: None,
+			label: Some(String::from(rules.label())),
+			style: ButtonStyle::Success,
+			url: None,
+		})],
+	})
+}
+
+/// Hashes the welcome message that contains the rules, to record which version of them was accepted.
+pub fn hash(welcome: &config::Welcome) -> String {
+	let mut hasher = Sha256::new();
+	hasher.update(welcome.source().content().unwrap_or_default());
+	if let Some(embeds) = welcome.source().embeds().and_then(|f| std::fs::read(f).ok()) {
+		hasher.update(embeds);
+	}
+	hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Gives the member the role for having accepted the rules, and records it.
+pub async fn handle(client: &Client, application: Id<ApplicationMarker>, context: Arc<Context>, interaction: Interaction) {
+	let content = match accept(client, &context, &interaction).await {
+		Ok(content) => {
+			context.metrics.command(ACCEPT, "success");
+			content
+		},
+		Err(content) => {
+			context.metrics.command(ACCEPT, "error");
+			content
+		},
+	};
+
+	let response = InteractionResponse {
+		kind: InteractionResponseType::ChannelMessageWithSource,
+		data: Some(InteractionResponseData {
+			content: Some(content),
+			flags: Some(MessageFlags::EPHEMERAL),
+			..Default::default()
+		}),
+	};
+
+	let r = client.interaction(application).create_response(interaction.id, &interaction.token, &response).await;
+	if let Err(e) = r {
+		error!(error = %e, "Couldn't respond to rules acceptance.");
+	}
+}
+
+/// Returns the message to show the member, as an error if the role couldn't be given.
+async fn accept(client: &Client, context: &Context, interaction: &Interaction) -> Result<String, String> {
+	let text = |key| context.text(interaction.locale.as_deref(), key, &[]);
+
+	let config = interaction.guild_id.and_then(|guild| context.config().guild(guild));
+	let (config, member) = match (config, &interaction.member) {
+		(Some(config), Some(member)) => (config, member),
+		_ => return Err(text("general.unconfigured_guild")),
+	};
+	let user = match &member.user {
+		Some(user) => user.id,
+		None => panic!("User data in member should be set!"),
+	};
+
+	let (welcome, rules) = match config.welcome().and_then(|w| Some((w, w.rules()?))) {
+		Some(found) => found,
+		None => {
+			warn!("Rules acceptance is no longer configured.");
+			return Err(text("rules.no_longer_needed"));
+		},
+	};
+
+	let guild = config.id();
+	let role = rules.role();
+	let hash = hash(welcome);
+
+	let previous = context.state.acceptance(guild, user).unwrap_or_else(|e| {
+		error!(error = %e, "Couldn't read rules acceptance from state!");
+		None
+	});
+	if member.roles.contains(&role) && previous.is_some_and(|a| a.hash() == hash) {
+		return Ok(text("rules.already_accepted"));
+	}
+
+	if !member.roles.contains(&role) {
+		// NOTE This requires the MANAGE_ROLES permission, and the role has to be below the bot's highest role.
+		info!(%user, %role, "Adding role for accepting the rules.");
+		if let Err(e) = client.add_guild_member_role(guild, user, role).await {
+			error!(%user, %role, error = %e, "Couldn't add role to member.");
+			return Err(text("rules.failed"));
+		}
+	}
+
+	let accepted = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+	if let Err(e) = context.state.set_acceptance(guild, user, Acceptance::new(hash, accepted)) {
+		error!(error = %e, "Couldn't write state!");
+	}
+
+	Ok(text("rules.accepted"))
+}
+
+/// Takes the role from every member who has it and forgets all acceptances, so that
+/// everyone has to accept the rules again. Returns the number of members affected.
+///
+/// The members are listed from the guild rather than the state, since the role may also
+/// have been given by hand or before the acceptances were recorded.
+pub async fn reset(client: &Client, context: &Context, config: &config::Guild) -> Result<usize, MembersError> {
+	// SAFETY The command checks that the rules are configured.
+	let role = config.welcome().and_then(|w| w.rules()).unwrap().role();
+	let guild = config.id();
+
+	info!(%role, "Collecting members for rules reset.");
+	let members: Vec<_> = all_members(client, guild).await?.into_iter()
+		.filter(|member| member.roles.contains(&role))
+		.map(|member| member.user.id)
+		.collect();
+
+	info!(count = members.len(), %role, "Resetting rules acceptances.");
+	for &user in &members {
+		// NOTE This requires the MANAGE_ROLES permission.
+		if let Err(e) = client.remove_guild_member_role(guild, user, role).await {
+			// The member may have left the guild since it was listed, so this isn't fatal.
+			warn!(%user, %role, error = %e, "Couldn't remove role from member.");
+		}
+	}
+
+	if let Err(e) = context.state.clear_acceptances(guild) {
+		error!(error = %e, "Couldn't write state!");
+	}
+
+	Ok(members.len())
+}
+
+#[check]
+async fn rules_reset_permission(ctx: &SlashContext<Arc<Context>>) -> Result<bool, DefaultError> {
+	let config = match guild_config(ctx).await {
+		Some(config) => config,
+		None => return Ok(false),
+	};
+
+	let rules = match config.welcome().and_then(|w| w.rules()) {
+		Some(rules) => rules,
+		None => {
+			let response = InteractionResponse {
+				kind: InteractionResponseType::ChannelMessageWithSource,
+				data: Some(InteractionResponseData {
+					content: Some(ctx.data.text(ctx.interaction.locale.as_deref(), "rules.not_configured", &[])),
+					flags: Some(MessageFlags::EPHEMERAL),
+					..Default::default()
+				}),
+			};
+
+			let r = ctx.interaction_client.create_response(ctx.interaction.id, &ctx.interaction.token, &response).await;
+			if let Err(e) = r {
+				error!(error = %e, "Couldn't respond to command.");
+			}
+			return Ok(false);
+		},
+	};
+
+	Ok(has_permission(ctx, &config, rules.permission().reset()).await)
+}
+
+#[command(chat, name = "reset")]
+#[description = "Require everyone to accept the rules again"]
+#[checks(rules_reset_permission)]
+async fn rules_reset(ctx: &mut SlashContext<Arc<Context>>) -> DefaultCommandResult {
+	let config = match guild_config(ctx).await {
+		Some(config) => config,
+		None => return Ok(()),
+	};
+
+	// Removing the role from everyone may take longer than Discord waits for a response.
+	let response = InteractionResponse {
+		kind: InteractionResponseType::DeferredChannelMessageWithSource,
+		data: Some(InteractionResponseData {
+			flags: Some(MessageFlags::EPHEMERAL),
+			..Default::default()
+		}),
+	};
+
+	let r = ctx.interaction_client.create_response(ctx.interaction.id, &ctx.interaction.token, &response).await;
+	if let Err(e) = r {
+		error!(error = %e, "Couldn't respond to command.");
+	}
+
+	let locale = ctx.interaction.locale.as_deref();
+	let content = match reset(ctx.http_client(), ctx.data, &config).await {
+		Ok(count) => ctx.data.text(locale, "rules.reset", &[("count", count.to_string())]),
+		Err(e) => {
+			error!(error = %e, "Couldn't reset the rules.");
+			ctx.data.text(locale, "rules.reset_failed", &[])
+		},
+	};
+	let r = ctx.interaction_client.update_response(&ctx.interaction.token)
+		.content(Some(&content)).expect("Response content was malformed.")
+		.await;
+	if let Err(e) = r {
+		error!(error = %e, "Couldn't update response to command.");
+	}
+
+	Ok(())
+}
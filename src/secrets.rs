@@ -3,12 +3,22 @@ use twilight_model::id::marker::ApplicationMarker;
 
 use serde::{Serialize, Deserialize};
 
+use tracing::{debug, info};
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
 pub const DEFAULT_PATH: &str = "secrets.toml";
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Secrets {
 	pub discord: Discord,
-	pub ebas: Ebas,
+	/// The default eBas credentials, which are only needed if a guild uses them.
+	pub ebas: Option<Ebas>,
+	/// Credentials for other eBas associations than the default one, by name.
+	#[serde(default)]
+	pub associations: HashMap<String, Ebas>,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -21,4 +31,199 @@ pub struct Discord {
 pub struct Ebas {
 	pub api_key: String,
 	pub id: String,
-}
\ No newline at end of file
+}
+
+/// The secrets file with every field optional, since they can be given in other ways.
+#[derive(Deserialize, Default)]
+struct PartialSecrets {
+	#[serde(default)]
+	discord: PartialDiscord,
+	#[serde(default)]
+	ebas: PartialEbas,
+	#[serde(default)]
+	associations: HashMap<String, PartialEbas>,
+}
+
+#[derive(Deserialize, Default)]
+struct PartialDiscord {
+	token: Option<String>,
+	application: Option<Id<ApplicationMarker>>,
+}
+
+#[derive(Deserialize, Default)]
+struct PartialEbas {
+	api_key: Option<String>,
+	id: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum SecretsError {
+	Io(PathBuf, std::io::Error),
+	Parse(toml::de::Error),
+	Missing(String),
+	Invalid(String, String),
+}
+
+impl fmt::Display for SecretsError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			SecretsError::Io(path, e) => write!(f, "couldn't read {}: {}", path.display(), e),
+			SecretsError::Parse(e) => write!(f, "couldn't parse secrets: {}", e),
+			SecretsError::Missing(name) => write!(f, "secret {} isn't set", name),
+			SecretsError::Invalid(name, reason) => write!(f, "secret {} is invalid: {}", name, reason),
+		}
+	}
+}
+
+impl std::error::Error for SecretsError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			SecretsError::Io(_, e) => Some(e),
+			SecretsError::Parse(e) => Some(e),
+			_ => None,
+		}
+	}
+}
+
+/// Where a secret was read from.
+enum Source {
+	Environment(String),
+	File(String, PathBuf),
+	SecretsFile,
+}
+
+impl fmt::Display for Source {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Source::Environment(var) => write!(f, "environment variable {}", var),
+			Source::File(var, path) => write!(f, "file {} from {}", path.display(), var),
+			Source::SecretsFile => write!(f, "secrets file"),
+		}
+	}
+}
+
+/// Reads the secrets, where each field is taken from the first of these that is set:
+///
+/// 1. The environment variable, e.g. `KODBOT_DISCORD_TOKEN`.
+/// 2. The file named by the environment variable with a `_FILE` suffix, e.g. `KODBOT_DISCORD_TOKEN_FILE`,
+///    which is how Docker secrets and systemd credentials are usually passed.
+/// 3. The secrets file at `path`, which doesn't have to exist.
+///
+/// The eBas credentials are only required for the associations in `associations`, where
+/// `None` is the default one. Other associations are read in the same way, from
+/// `[associations.<name>]` in the file or e.g. `KODBOT_EBAS_<NAME>_API_KEY` in the environment.
+pub fn load<P: AsRef<Path>>(path: P, associations: &[Option<String>]) -> Result<Secrets, SecretsError> {
+	let path = path.as_ref();
+	let mut partial: PartialSecrets = match std::fs::read_to_string(path) {
+		Ok(s) => toml::from_str(&s).map_err(SecretsError::Parse)?,
+		Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+			debug!(path = %path.display(), "No secrets file found.");
+			PartialSecrets::default()
+		},
+		Err(e) => return Err(SecretsError::Io(path.to_path_buf(), e)),
+	};
+
+	let application = partial.discord.application.map(|a| a.to_string());
+	let application = required("discord.application", "KODBOT_DISCORD_APPLICATION", application)?
+		.trim()
+		.parse()
+		.map_err(|_| SecretsError::Invalid(String::from("discord.application"), String::from("expected a non zero integer")))?;
+
+	let mut resolved = HashMap::new();
+	for name in associations.iter().flatten() {
+		let association = partial.associations.remove(name).unwrap_or_default();
+		let var = name.to_uppercase().replace(|c: char| !c.is_ascii_alphanumeric(), "_");
+		resolved.insert(name.clone(), Ebas {
+			api_key: required(&format!("associations.{}.api_key", name), &format!("KODBOT_EBAS_{}_API_KEY", var), association.api_key)?,
+			id: required(&format!("associations.{}.id", name), &format!("KODBOT_EBAS_{}_ID", var), association.id)?,
+		});
+	}
+
+	let ebas = if associations.contains(&None) {
+		Some(Ebas {
+			api_key: required("ebas.api_key", "KODBOT_EBAS_API_KEY", partial.ebas.api_key)?,
+			id: required("ebas.id", "KODBOT_EBAS_ID", partial.ebas.id)?,
+		})
+	} else {
+		None
+	};
+
+	Ok(Secrets {
+		discord: Discord {
+			token: required("discord.token", "KODBOT_DISCORD_TOKEN", partial.discord.token)?,
+			application,
+		},
+		ebas,
+		associations: resolved,
+	})
+}
+
+impl Secrets {
+	/// The eBas credentials of the named association, or the default ones if no name is given.
+	pub fn ebas(&self, association: Option<&str>) -> Option<&Ebas> {
+		match association {
+			Some(name) => self.associations.get(name),
+			None => self.ebas.as_ref(),
+		}
+	}
+}
+
+fn required(name: &str, var: &str, file: Option<String>) -> Result<String, SecretsError> {
+	resolve(name, var, file)?.ok_or_else(|| SecretsError::Missing(String::from(name)))
+}
+
+/// Looks up a secret from the environment, falling back to the value from the secrets file.
+fn resolve(name: &str, var: &str, file: Option<String>) -> Result<Option<String>, SecretsError> {
+	let (value, source) = if let Ok(value) = std::env::var(var) {
+		(value, Source::Environment(String::from(var)))
+	} else if let Some(path) = std::env::var_os(format!("{}_FILE", var)) {
+		let path = PathBuf::from(path);
+		let value = std::fs::read_to_string(&path).map_err(|e| SecretsError::Io(path.clone(), e))?;
+		// Files almost always end with a newline, which is never part of the secret.
+		let value = String::from(value.trim_end_matches(['\n', '\r']));
+		(value, Source::File(format!("{}_FILE", var), path))
+	} else if let Some(value) = file {
+		(value, Source::SecretsFile)
+	} else {
+		return Ok(None);
+	};
+
+	// Only ever log where the secret came from, never its value.
+	info!(secret = name, %source, "Read secret.");
+	Ok(Some(value))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const DISCORD: &str = "[discord]\ntoken = \"token\"\napplication = \"1\"\n";
+
+	fn load_from(name: &str, contents: &str, associations: &[Option<String>]) -> Result<Secrets, SecretsError> {
+		let path = std::env::temp_dir().join(format!("kodbot-secrets-{}-{}.toml", name, std::process::id()));
+		std::fs::write(&path, contents).unwrap();
+		let secrets = load(&path, associations);
+		std::fs::remove_file(&path).unwrap();
+		secrets
+	}
+
+	#[test]
+	fn default_ebas_is_optional_when_unused() {
+		let secrets = load_from("unused", DISCORD, &[]).unwrap();
+		assert!(secrets.ebas(None).is_none());
+	}
+
+	#[test]
+	fn default_ebas_is_required_when_used() {
+		let result = load_from("used", DISCORD, &[None]);
+		assert!(matches!(result, Err(SecretsError::Missing(name)) if name == "ebas.api_key"));
+	}
+
+	#[test]
+	fn named_association_doesnt_need_default_ebas() {
+		let contents = format!("{}\n[associations.camp]\napi_key = \"key\"\nid = \"2\"\n", DISCORD);
+		let secrets = load_from("named", &contents, &[Some(String::from("camp"))]).unwrap();
+		assert!(secrets.ebas(None).is_none());
+		assert_eq!(secrets.ebas(Some("camp")).unwrap().id, "2");
+	}
+}
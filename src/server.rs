@@ -0,0 +1,50 @@
+use axum::{Router, Json};
+use axum::extract::State;
+use axum::http::{StatusCode, header};
+use axum::response::IntoResponse;
+use axum::routing::get;
+
+use tracing::{info, error};
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use crate::Context;
+
+/// Serves `/healthz` and `/metrics` on the given address until the process exits.
+pub async fn serve(address: SocketAddr, context: Arc<Context>) {
+	let app = Router::new()
+		.route("/healthz", get(healthz))
+		.route("/metrics", get(metrics))
+		.with_state(context);
+
+	info!(%address, "Starting HTTP listener.");
+	let server = match axum::Server::try_bind(&address) {
+		Ok(server) => server,
+		Err(e) => {
+			error!(%address, error = %e, "Couldn't bind HTTP listener.");
+			return;
+		},
+	};
+
+	if let Err(e) = server.serve(app.into_make_service()).await {
+		error!(error = %e, "HTTP listener stopped.");
+	}
+}
+
+async fn healthz(State(context): State<Arc<Context>>) -> impl IntoResponse {
+	let report = context.health.report();
+	let status = if report.healthy {
+		StatusCode::OK
+	} else {
+		StatusCode::SERVICE_UNAVAILABLE
+	};
+
+	(status, Json(report))
+}
+
+async fn metrics(State(context): State<Arc<Context>>) -> impl IntoResponse {
+	(
+		[(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+		context.metrics.encode(),
+	)
+}
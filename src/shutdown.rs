@@ -0,0 +1,59 @@
+use twilight_gateway::{Shard, CloseFrame, Message};
+use twilight_gateway::error::ReceiveMessageErrorType;
+
+use tracing::{debug, info, warn};
+
+use std::time::Duration;
+
+/// How long to wait for Discord to acknowledge that the gateway connection is closed.
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Resolves when the process is asked to stop, either by SIGTERM (e.g. `docker compose down`)
+/// or by ctrl-c.
+pub async fn signal() {
+	#[cfg(unix)]
+	{
+		use tokio::signal::unix::{signal, SignalKind};
+
+		let mut terminate = signal(SignalKind::terminate()).expect("Couldn't install SIGTERM handler.");
+		tokio::select! {
+			_ = terminate.recv() => info!("Received SIGTERM."),
+			_ = tokio::signal::ctrl_c() => info!("Received ctrl-c."),
+		}
+	}
+
+	#[cfg(not(unix))]
+	{
+		tokio::signal::ctrl_c().await.expect("Couldn't install ctrl-c handler.");
+		info!("Received ctrl-c.");
+	}
+}
+
+/// Closes the gateway connection and waits for Discord to acknowledge it.
+pub async fn close_shard(shard: &mut Shard) {
+	if let Err(e) = shard.close(CloseFrame::NORMAL).await {
+		warn!(error = %e, "Couldn't send close frame to the gateway.");
+		return;
+	}
+
+	let acknowledged = tokio::time::timeout(CLOSE_TIMEOUT, async {
+		loop {
+			match shard.next_message().await {
+				// Further calls to next_message would reconnect the shard.
+				Ok(Message::Close(frame)) => {
+					debug!(?frame, "Gateway acknowledged close.");
+					break;
+				},
+				Ok(Message::Text(_)) => (),
+				Err(e) if matches!(e.kind(), ReceiveMessageErrorType::Io) => break,
+				Err(e) => warn!(error = %e, "Encountered error when closing the gateway connection."),
+			}
+		}
+	}).await;
+
+	if acknowledged.is_err() {
+		warn!("Gateway didn't acknowledge close in time.");
+	} else {
+		info!("Closed the gateway connection.");
+	}
+}
@@ -0,0 +1,283 @@
+use serde::{Serialize, Deserialize};
+
+use twilight_model::id::Id;
+use twilight_model::id::marker::{GuildMarker, UserMarker};
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use super::{Storage, StateError, MigrationContext, Posted, Acceptance};
+
+/// The schema version written by this build.
+///
+/// The state is written when changed and read when the bot starts. If the data
+/// structures below are changed, bump this version and append a migration to
+/// [`MIGRATIONS`] that upgrades a file of the previous version.
+pub const VERSION: u32 = 6;
+
+/// Upgrades a stored state from the version at its index in [`MIGRATIONS`] to the next one.
+type Migration = fn(&mut toml::Table, &MigrationContext) -> Result<(), String>;
+
+const MIGRATIONS: &[Migration] = &[
+	// Version 0 is the format from before the state was versioned, it only lacks the version key.
+	|_, _| Ok(()),
+	// Version 2 keeps track of the registered commands per guild.
+	|table, _| {
+		table.entry("commands").or_insert_with(|| toml::Value::Table(toml::Table::new()));
+		Ok(())
+	},
+	// Version 3 keeps all state per guild.
+	|table, context| {
+		let mut guilds = toml::Table::new();
+
+		if let Some(toml::Value::Table(commands)) = table.remove("commands") {
+			for (guild, hash) in commands {
+				let mut state = toml::Table::new();
+				state.insert(String::from("commands"), hash);
+				guilds.insert(guild, toml::Value::Table(state));
+			}
+		}
+
+		if let Some(welcome) = table.remove("welcome") {
+			let guild = context.single_guild()?.to_string();
+			let state = guilds.entry(guild).or_insert_with(|| toml::Value::Table(toml::Table::new()));
+			// SAFETY All values in guilds are inserted as tables above.
+			state.as_table_mut().unwrap().insert(String::from("welcome"), welcome);
+		}
+
+		table.insert(String::from("guilds"), toml::Value::Table(guilds));
+		Ok(())
+	},
+	// Version 4 allows the welcome message to be split into several messages.
+	|table, _| {
+		let guilds = match table.get_mut("guilds") {
+			Some(toml::Value::Table(guilds)) => guilds,
+			_ => return Ok(()),
+		};
+
+		for (_, guild) in guilds.iter_mut() {
+			if let Some(toml::Value::Table(welcome)) = guild.get_mut("welcome") {
+				if let Some(message) = welcome.remove("message") {
+					welcome.insert(String::from("messages"), toml::Value::Array(vec![message]));
+				}
+			}
+		}
+
+		Ok(())
+	},
+	// Version 5 records who has accepted the rules, which nobody has to begin with.
+	|_, _| Ok(()),
+	// Version 6 keeps the sticky messages, of which there are none to begin with.
+	|_, _| Ok(()),
+];
+
+#[derive(Deserialize, Serialize)]
+pub struct State {
+	version: u32,
+	// Guild ids as strings, since TOML keys have to be strings.
+	guilds: BTreeMap<String, GuildState>,
+}
+
+#[derive(Deserialize, Serialize, Default)]
+struct GuildState {
+	welcome: Option<Posted>,
+	commands: Option<String>,
+	// User ids as strings, like the guild ids.
+	#[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+	rules: BTreeMap<String, Acceptance>,
+	// Keyed by the name of the sticky message.
+	#[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+	sticky: BTreeMap<String, Posted>,
+}
+
+/// Keeps the whole state in memory and rewrites the TOML file on every change.
+pub struct FileStorage {
+	path: PathBuf,
+	state: RwLock<State>,
+}
+
+impl State {
+	fn new() -> State {
+		State {
+			version: VERSION,
+			guilds: BTreeMap::new(),
+		}
+	}
+
+	fn guild(&self, guild: Id<GuildMarker>) -> Option<&GuildState> {
+		self.guilds.get(&guild.to_string())
+	}
+
+	fn guild_mut(&mut self, guild: Id<GuildMarker>) -> &mut GuildState {
+		self.guilds.entry(guild.to_string()).or_default()
+	}
+}
+
+impl FileStorage {
+	pub fn open<P: AsRef<Path>>(path: P, context: &MigrationContext) -> Result<FileStorage, StateError> {
+		let path = path.as_ref().to_path_buf();
+		let state = match from_file(&path, context) {
+			Ok(state) => state,
+			Err(StateError::NotFound) => {
+				tracing::info!(path = %path.display(), "No state file found, starting with empty state.");
+				State::new()
+			},
+			Err(e) => return Err(e),
+		};
+
+		Ok(FileStorage {
+			path,
+			state: RwLock::new(state),
+		})
+	}
+
+	fn update<F: FnOnce(&mut State)>(&self, f: F) -> Result<(), StateError> {
+		let mut state = self.state.write().unwrap();
+		f(&mut state);
+		to_file(&self.path, &state)
+	}
+}
+
+impl Storage for FileStorage {
+	fn welcome(&self, guild: Id<GuildMarker>) -> Result<Option<Posted>, StateError> {
+		Ok(self.state.read().unwrap().guild(guild).and_then(|g| g.welcome.clone()))
+	}
+
+	fn set_welcome(&self, guild: Id<GuildMarker>, welcome: Posted) -> Result<(), StateError> {
+		self.update(|state| state.guild_mut(guild).welcome = Some(welcome))
+	}
+
+	fn sticky(&self, guild: Id<GuildMarker>, name: &str) -> Result<Option<Posted>, StateError> {
+		Ok(self.state.read().unwrap().guild(guild).and_then(|g| g.sticky.get(name).cloned()))
+	}
+
+	fn set_sticky(&self, guild: Id<GuildMarker>, name: &str, sticky: Posted) -> Result<(), StateError> {
+		self.update(|state| {
+			state.guild_mut(guild).sticky.insert(String::from(name), sticky);
+		})
+	}
+
+	fn stickies(&self, guild: Id<GuildMarker>) -> Result<Vec<(String, Posted)>, StateError> {
+		Ok(self.state.read().unwrap().guild(guild)
+			.map(|g| g.sticky.iter().map(|(name, sticky)| (name.clone(), sticky.clone())).collect())
+			.unwrap_or_default())
+	}
+
+	fn commands_hash(&self, guild: Id<GuildMarker>) -> Result<Option<String>, StateError> {
+		Ok(self.state.read().unwrap().guild(guild).and_then(|g| g.commands.clone()))
+	}
+
+	fn set_commands_hash(&self, guild: Id<GuildMarker>, hash: Option<String>) -> Result<(), StateError> {
+		self.update(|state| state.guild_mut(guild).commands = hash)
+	}
+
+	fn acceptance(&self, guild: Id<GuildMarker>, user: Id<UserMarker>) -> Result<Option<Acceptance>, StateError> {
+		Ok(self.state.read().unwrap().guild(guild).and_then(|g| g.rules.get(&user.to_string()).cloned()))
+	}
+
+	fn set_acceptance(&self, guild: Id<GuildMarker>, user: Id<UserMarker>, acceptance: Acceptance) -> Result<(), StateError> {
+		self.update(|state| {
+			state.guild_mut(guild).rules.insert(user.to_string(), acceptance);
+		})
+	}
+
+	fn acceptances(&self, guild: Id<GuildMarker>) -> Result<Vec<(Id<UserMarker>, Acceptance)>, StateError> {
+		let state = self.state.read().unwrap();
+		let rules = match state.guild(guild) {
+			Some(g) => &g.rules,
+			None => return Ok(Vec::new()),
+		};
+
+		// The keys are only ever written from valid ids.
+		Ok(rules.iter()
+			.filter_map(|(user, acceptance)| Some((user.parse().ok()?, acceptance.clone())))
+			.collect())
+	}
+
+	fn clear_acceptances(&self, guild: Id<GuildMarker>) -> Result<(), StateError> {
+		self.update(|state| state.guild_mut(guild).rules.clear())
+	}
+
+	fn flush(&self) -> Result<(), StateError> {
+		to_file(&self.path, &self.state.read().unwrap())
+	}
+}
+
+/// Runs the migrations needed to bring a stored state up to [`VERSION`].
+fn migrate(table: &mut toml::Table, context: &MigrationContext) -> Result<(), StateError> {
+	let version = match table.get("version") {
+		None => 0,
+		Some(toml::Value::Integer(v)) => u64::try_from(*v).map_err(|_| StateError::InvalidVersion(v.to_string()))?,
+		Some(v) => return Err(StateError::InvalidVersion(v.to_string())),
+	};
+
+	if version > u64::from(VERSION) {
+		return Err(StateError::UnsupportedVersion(version));
+	}
+
+	for (from, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+		let from = from as u32;
+		tracing::info!(from, to = from + 1, "Migrating state.");
+		migration(table, context).map_err(|reason| StateError::Migration { from, reason })?;
+		table.insert(String::from("version"), toml::Value::Integer(i64::from(from) + 1));
+	}
+
+	Ok(())
+}
+
+pub fn from_file<P: AsRef<Path>>(path: P, context: &MigrationContext) -> Result<State, StateError> {
+	let s = std::fs::read_to_string(&path)?;
+	let mut table: toml::Table = toml::from_str(&s)?;
+	migrate(&mut table, context)?;
+	Ok(toml::Value::Table(table).try_into()?)
+}
+
+/// Writes the state to a temporary file next to `path` and then renames it,
+/// so that a crash never leaves a partially written state behind.
+pub fn to_file<P: AsRef<Path>>(path: P, state: &State) -> Result<(), StateError> {
+	let path = path.as_ref();
+	let s = toml::to_string(state)?;
+
+	let mut tmp = path.as_os_str().to_owned();
+	tmp.push(".tmp");
+
+	{
+		use std::io::Write;
+
+		let mut file = std::fs::File::create(&tmp)?;
+		file.write_all(s.as_bytes())?;
+		file.sync_all()?;
+	}
+
+	Ok(std::fs::rename(&tmp, path)?)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn migrates_version_1() {
+		let mut table: toml::Table = toml::from_str("version = 1\n\n[welcome]\nmessage = \"42\"\n").unwrap();
+		let context = MigrationContext::new(vec![Id::new(1)]);
+		migrate(&mut table, &context).unwrap();
+
+		let state: State = toml::Value::Table(table).try_into().unwrap();
+		assert_eq!(state.version, VERSION);
+		let guild = state.guild(Id::new(1)).unwrap();
+		assert_eq!(guild.welcome.as_ref().unwrap().messages(), [Id::new(42)]);
+		assert!(guild.commands.is_none());
+	}
+
+	#[test]
+	fn reports_stored_version() {
+		let context = MigrationContext::new(vec![Id::new(1)]);
+
+		let mut table: toml::Table = toml::from_str("version = -1").unwrap();
+		assert!(matches!(migrate(&mut table, &context), Err(StateError::InvalidVersion(v)) if v == "-1"));
+
+		let mut table: toml::Table = toml::from_str("version = 100").unwrap();
+		assert!(matches!(migrate(&mut table, &context), Err(StateError::UnsupportedVersion(100))));
+	}
+}
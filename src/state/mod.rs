@@ -0,0 +1,233 @@
+use twilight_model::id::Id;
+use twilight_model::id::marker::{GuildMarker, MessageMarker, UserMarker};
+
+use serde::{Serialize, Deserialize};
+
+use std::path::Path;
+
+use crate::config::StateBackend;
+
+pub mod file;
+pub mod sqlite;
+
+pub const DEFAULT_PATH: &str = "state.toml";
+
+/// Persistent storage for the state of the bot.
+///
+/// Every setter persists the change before returning, so the state is consistent
+/// if the bot is restarted at any point.
+pub trait Storage: Send + Sync {
+	fn welcome(&self, guild: Id<GuildMarker>) -> Result<Option<Posted>, StateError>;
+
+	fn set_welcome(&self, guild: Id<GuildMarker>, welcome: Posted) -> Result<(), StateError>;
+
+	/// The messages posted for the sticky message with the name in the configuration.
+	fn sticky(&self, guild: Id<GuildMarker>, name: &str) -> Result<Option<Posted>, StateError>;
+
+	fn set_sticky(&self, guild: Id<GuildMarker>, name: &str, sticky: Posted) -> Result<(), StateError>;
+
+	fn stickies(&self, guild: Id<GuildMarker>) -> Result<Vec<(String, Posted)>, StateError>;
+
+	/// The hash of the command definitions that were last registered in the guild.
+	fn commands_hash(&self, guild: Id<GuildMarker>) -> Result<Option<String>, StateError>;
+
+	fn set_commands_hash(&self, guild: Id<GuildMarker>, hash: Option<String>) -> Result<(), StateError>;
+
+	/// When the user last accepted the rules of the guild, if they have.
+	fn acceptance(&self, guild: Id<GuildMarker>, user: Id<UserMarker>) -> Result<Option<Acceptance>, StateError>;
+
+	fn set_acceptance(&self, guild: Id<GuildMarker>, user: Id<UserMarker>, acceptance: Acceptance) -> Result<(), StateError>;
+
+	fn acceptances(&self, guild: Id<GuildMarker>) -> Result<Vec<(Id<UserMarker>, Acceptance)>, StateError>;
+
+	/// Forgets every acceptance of the rules of the guild, so that everyone has to accept them again.
+	fn clear_acceptances(&self, guild: Id<GuildMarker>) -> Result<(), StateError>;
+
+	/// Makes sure that everything is written to disk, used when shutting down.
+	fn flush(&self) -> Result<(), StateError>;
+}
+
+/// What migrations may need to know about the configuration.
+pub struct MigrationContext {
+	guilds: Vec<Id<GuildMarker>>,
+}
+
+/// The messages that make up the welcome message or a sticky message of a guild, in the order they were posted.
+#[derive(Deserialize, Serialize, Clone, Default)]
+pub struct Posted {
+	messages: Vec<Id<MessageMarker>>,
+}
+
+/// That a user accepted the rules, and which version of them.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Acceptance {
+	// The hash of the rules that were accepted.
+	hash: String,
+	// Seconds since the Unix epoch.
+	accepted: u64,
+}
+
+#[derive(Debug)]
+pub enum StateError {
+	NotFound,
+	Io(std::io::Error),
+	Deserialize(toml::de::Error),
+	Serialize(toml::ser::Error),
+	Sqlite(rusqlite::Error),
+	/// The stored version isn't a version number at all.
+	InvalidVersion(String),
+	/// The state was written by a newer version of the bot.
+	UnsupportedVersion(u64),
+	Migration {
+		from: u32,
+		reason: String,
+	},
+}
+
+use std::convert::From;
+impl From<std::io::Error> for StateError {
+	fn from(error: std::io::Error) -> StateError {
+		match error.kind() {
+			std::io::ErrorKind::NotFound => StateError::NotFound,
+			_ => StateError::Io(error),
+		}
+	}
+}
+
+impl From<toml::de::Error> for StateError {
+	fn from(error: toml::de::Error) -> StateError {
+		StateError::Deserialize(error)
+	}
+}
+
+impl From<toml::ser::Error> for StateError {
+	fn from(error: toml::ser::Error) -> StateError {
+		StateError::Serialize(error)
+	}
+}
+
+impl From<rusqlite::Error> for StateError {
+	fn from(error: rusqlite::Error) -> StateError {
+		StateError::Sqlite(error)
+	}
+}
+
+impl std::fmt::Display for StateError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			StateError::NotFound => write!(f, "state file not found"),
+			StateError::Io(e) => write!(f, "couldn't access state file: {}", e),
+			StateError::Deserialize(e) => write!(f, "couldn't parse state: {}", e),
+			StateError::Serialize(e) => write!(f, "couldn't serialize state: {}", e),
+			StateError::Sqlite(e) => write!(f, "state database error: {}", e),
+			StateError::InvalidVersion(v) => write!(f, "state has invalid version {}", v),
+			StateError::UnsupportedVersion(v) => write!(f, "state has version {} which is newer than this build supports", v),
+			StateError::Migration { from, reason } => write!(f, "couldn't migrate state from version {}: {}", from, reason),
+		}
+	}
+}
+
+impl std::error::Error for StateError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			StateError::Io(e) => Some(e),
+			StateError::Deserialize(e) => Some(e),
+			StateError::Serialize(e) => Some(e),
+			StateError::Sqlite(e) => Some(e),
+			_ => None,
+		}
+	}
+}
+
+impl MigrationContext {
+	pub fn new(guilds: Vec<Id<GuildMarker>>) -> MigrationContext {
+		MigrationContext {
+			guilds,
+		}
+	}
+
+	/// The guild that state from before the bot supported several guilds belongs to.
+	pub fn single_guild(&self) -> Result<Id<GuildMarker>, String> {
+		match self.guilds.as_slice() {
+			[guild] => Ok(*guild),
+			_ => Err(String::from("the state is from before several guilds were supported, \
+				so start the bot once with only the previously used guild configured")),
+		}
+	}
+}
+
+impl Posted {
+	pub fn new(messages: Vec<Id<MessageMarker>>) -> Posted {
+		Posted {
+			messages,
+		}
+	}
+
+	pub fn messages(&self) -> &[Id<MessageMarker>] {
+		&self.messages
+	}
+
+	pub fn set_messages(&mut self, messages: Vec<Id<MessageMarker>>) {
+		self.messages = messages
+	}
+}
+
+impl Acceptance {
+	pub fn new(hash: String, accepted: u64) -> Acceptance {
+		Acceptance {
+			hash,
+			accepted,
+		}
+	}
+
+	pub fn hash(&self) -> &str {
+		&self.hash
+	}
+
+	pub fn accepted(&self) -> u64 {
+		self.accepted
+	}
+}
+
+/// Picks the backend from the configuration, or from the file extension of `path`
+/// if it isn't configured. Files ending in `.db`, `.sqlite` or `.sqlite3` use SQLite.
+pub fn backend<P: AsRef<Path>>(path: P, configured: Option<StateBackend>) -> StateBackend {
+	if let Some(backend) = configured {
+		return backend;
+	}
+
+	match path.as_ref().extension().and_then(|e| e.to_str()) {
+		Some("db" | "sqlite" | "sqlite3") => StateBackend::Sqlite,
+		_ => StateBackend::Toml,
+	}
+}
+
+/// Opens the state at `path`, creating it if it doesn't exist.
+pub fn open<P: AsRef<Path>>(path: P, backend: StateBackend, context: &MigrationContext) -> Result<Box<dyn Storage>, StateError> {
+	Ok(match backend {
+		StateBackend::Toml => Box::new(file::FileStorage::open(path, context)?),
+		StateBackend::Sqlite => Box::new(sqlite::SqliteStorage::open(path, context)?),
+	})
+}
+
+/// Copies everything from one storage to another, used to move between backends.
+///
+/// Command hashes aren't copied, which only means that the commands are registered
+/// again the next time the bot starts.
+pub fn copy(from: &dyn Storage, to: &dyn Storage, guilds: &[Id<GuildMarker>]) -> Result<(), StateError> {
+	for &guild in guilds {
+		if let Some(welcome) = from.welcome(guild)? {
+			to.set_welcome(guild, welcome)?;
+		}
+
+		for (name, sticky) in from.stickies(guild)? {
+			to.set_sticky(guild, &name, sticky)?;
+		}
+
+		for (user, acceptance) in from.acceptances(guild)? {
+			to.set_acceptance(guild, user, acceptance)?;
+		}
+	}
+
+	to.flush()
+}
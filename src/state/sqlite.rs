@@ -0,0 +1,268 @@
+use twilight_model::id::Id;
+use twilight_model::id::marker::{GuildMarker, UserMarker};
+
+use rusqlite::{Connection, OptionalExtension, Transaction, params};
+
+use std::path::Path;
+use std::sync::Mutex;
+
+use super::{Storage, StateError, MigrationContext, Posted, Acceptance};
+
+/// Upgrades the database from the version at its index in [`MIGRATIONS`] to the next one.
+enum Migration {
+	Sql(&'static str),
+	/// For migrations that need more than SQL, e.g. information from the configuration.
+	Code(fn(&Transaction, &MigrationContext) -> Result<(), StateError>),
+}
+
+/// The current version is kept in `PRAGMA user_version`. Never change a migration that
+/// has been released, append a new one instead.
+const MIGRATIONS: &[Migration] = &[
+	Migration::Sql("CREATE TABLE welcome (
+		id INTEGER PRIMARY KEY CHECK (id = 0),
+		message INTEGER NOT NULL
+	);"),
+	Migration::Sql("CREATE TABLE commands (
+		guild INTEGER PRIMARY KEY,
+		hash TEXT NOT NULL
+	);"),
+	// The welcome message is kept per guild.
+	Migration::Code(|transaction, context| {
+		let message: Option<i64> = transaction
+			.query_row("SELECT message FROM welcome WHERE id = 0", [], |row| row.get(0))
+			.optional()?;
+
+		transaction.execute_batch("DROP TABLE welcome;
+			CREATE TABLE welcome (
+				guild INTEGER PRIMARY KEY,
+				message INTEGER NOT NULL
+			);")?;
+
+		if let Some(message) = message {
+			let guild = context.single_guild()
+				.map_err(|reason| StateError::Migration { from: 2, reason })?;
+			transaction.execute("INSERT INTO welcome (guild, message) VALUES (?1, ?2)", params![from_id(guild), message])?;
+		}
+
+		Ok(())
+	}),
+	// The welcome message can be split into several messages, kept in the order they were posted.
+	Migration::Sql("CREATE TABLE welcome_messages (
+		guild INTEGER NOT NULL,
+		position INTEGER NOT NULL,
+		message INTEGER NOT NULL,
+		PRIMARY KEY (guild, position)
+	);
+	INSERT INTO welcome_messages (guild, position, message) SELECT guild, 0, message FROM welcome;
+	DROP TABLE welcome;"),
+	Migration::Sql("CREATE TABLE rules_acceptances (
+		guild INTEGER NOT NULL,
+		user INTEGER NOT NULL,
+		hash TEXT NOT NULL,
+		accepted INTEGER NOT NULL,
+		PRIMARY KEY (guild, user)
+	);"),
+	Migration::Sql("CREATE TABLE sticky_messages (
+		guild INTEGER NOT NULL,
+		name TEXT NOT NULL,
+		position INTEGER NOT NULL,
+		message INTEGER NOT NULL,
+		PRIMARY KEY (guild, name, position)
+	);"),
+];
+
+/// Keeps the state in an embedded SQLite database.
+pub struct SqliteStorage {
+	connection: Mutex<Connection>,
+}
+
+impl SqliteStorage {
+	pub fn open<P: AsRef<Path>>(path: P, context: &MigrationContext) -> Result<SqliteStorage, StateError> {
+		let mut connection = Connection::open(path)?;
+		connection.pragma_update(None, "journal_mode", "WAL")?;
+		migrate(&mut connection, context)?;
+
+		Ok(SqliteStorage {
+			connection: Mutex::new(connection),
+		})
+	}
+}
+
+impl Storage for SqliteStorage {
+	fn welcome(&self, guild: Id<GuildMarker>) -> Result<Option<Posted>, StateError> {
+		let connection = self.connection.lock().unwrap();
+		let mut statement = connection
+			.prepare("SELECT message FROM welcome_messages WHERE guild = ?1 ORDER BY position")?;
+		let messages = statement
+			.query_map(params![from_id(guild)], |row| to_id(0, row.get(0)?))?
+			.collect::<Result<Vec<_>, _>>()?;
+
+		// A guild without rows has never posted a welcome message.
+		Ok((!messages.is_empty()).then(|| Posted::new(messages)))
+	}
+
+	fn set_welcome(&self, guild: Id<GuildMarker>, welcome: Posted) -> Result<(), StateError> {
+		let mut connection = self.connection.lock().unwrap();
+		let transaction = connection.transaction()?;
+		transaction.execute("DELETE FROM welcome_messages WHERE guild = ?1", params![from_id(guild)])?;
+		for (position, &message) in welcome.messages().iter().enumerate() {
+			transaction.execute(
+				"INSERT INTO welcome_messages (guild, position, message) VALUES (?1, ?2, ?3)",
+				params![from_id(guild), position as i64, from_id(message)],
+			)?;
+		}
+		transaction.commit()?;
+
+		Ok(())
+	}
+
+	fn sticky(&self, guild: Id<GuildMarker>, name: &str) -> Result<Option<Posted>, StateError> {
+		let connection = self.connection.lock().unwrap();
+		let mut statement = connection
+			.prepare("SELECT message FROM sticky_messages WHERE guild = ?1 AND name = ?2 ORDER BY position")?;
+		let messages = statement
+			.query_map(params![from_id(guild), name], |row| to_id(0, row.get(0)?))?
+			.collect::<Result<Vec<_>, _>>()?;
+
+		Ok((!messages.is_empty()).then(|| Posted::new(messages)))
+	}
+
+	fn set_sticky(&self, guild: Id<GuildMarker>, name: &str, sticky: Posted) -> Result<(), StateError> {
+		let mut connection = self.connection.lock().unwrap();
+		let transaction = connection.transaction()?;
+		transaction.execute("DELETE FROM sticky_messages WHERE guild = ?1 AND name = ?2", params![from_id(guild), name])?;
+		for (position, &message) in sticky.messages().iter().enumerate() {
+			transaction.execute(
+				"INSERT INTO sticky_messages (guild, name, position, message) VALUES (?1, ?2, ?3, ?4)",
+				params![from_id(guild), name, position as i64, from_id(message)],
+			)?;
+		}
+		transaction.commit()?;
+
+		Ok(())
+	}
+
+	fn stickies(&self, guild: Id<GuildMarker>) -> Result<Vec<(String, Posted)>, StateError> {
+		let connection = self.connection.lock().unwrap();
+		let mut statement = connection
+			.prepare("SELECT name, message FROM sticky_messages WHERE guild = ?1 ORDER BY name, position")?;
+		let rows = statement
+			.query_map(params![from_id(guild)], |row| Ok((row.get::<_, String>(0)?, to_id(1, row.get(1)?)?)))?
+			.collect::<Result<Vec<_>, _>>()?;
+
+		let mut stickies: Vec<(String, Vec<_>)> = Vec::new();
+		for (name, message) in rows {
+			match stickies.last_mut() {
+				Some((last, messages)) if *last == name => messages.push(message),
+				_ => stickies.push((name, vec![message])),
+			}
+		}
+
+		Ok(stickies.into_iter().map(|(name, messages)| (name, Posted::new(messages))).collect())
+	}
+
+	fn commands_hash(&self, guild: Id<GuildMarker>) -> Result<Option<String>, StateError> {
+		let connection = self.connection.lock().unwrap();
+		let hash = connection
+			.query_row("SELECT hash FROM commands WHERE guild = ?1", params![from_id(guild)], |row| row.get(0))
+			.optional()?;
+
+		Ok(hash)
+	}
+
+	fn set_commands_hash(&self, guild: Id<GuildMarker>, hash: Option<String>) -> Result<(), StateError> {
+		let connection = self.connection.lock().unwrap();
+		match hash {
+			Some(hash) => connection.execute(
+				"INSERT INTO commands (guild, hash) VALUES (?1, ?2)
+				ON CONFLICT (guild) DO UPDATE SET hash = excluded.hash",
+				params![from_id(guild), hash],
+			)?,
+			None => connection.execute("DELETE FROM commands WHERE guild = ?1", params![from_id(guild)])?,
+		};
+
+		Ok(())
+	}
+
+	fn acceptance(&self, guild: Id<GuildMarker>, user: Id<UserMarker>) -> Result<Option<Acceptance>, StateError> {
+		let connection = self.connection.lock().unwrap();
+		let acceptance = connection
+			.query_row(
+				"SELECT hash, accepted FROM rules_acceptances WHERE guild = ?1 AND user = ?2",
+				params![from_id(guild), from_id(user)],
+				|row| Ok(Acceptance::new(row.get(0)?, row.get::<_, i64>(1)? as u64)),
+			)
+			.optional()?;
+
+		Ok(acceptance)
+	}
+
+	fn set_acceptance(&self, guild: Id<GuildMarker>, user: Id<UserMarker>, acceptance: Acceptance) -> Result<(), StateError> {
+		let connection = self.connection.lock().unwrap();
+		connection.execute(
+			"INSERT INTO rules_acceptances (guild, user, hash, accepted) VALUES (?1, ?2, ?3, ?4)
+			ON CONFLICT (guild, user) DO UPDATE SET hash = excluded.hash, accepted = excluded.accepted",
+			params![from_id(guild), from_id(user), acceptance.hash(), acceptance.accepted() as i64],
+		)?;
+
+		Ok(())
+	}
+
+	fn acceptances(&self, guild: Id<GuildMarker>) -> Result<Vec<(Id<UserMarker>, Acceptance)>, StateError> {
+		let connection = self.connection.lock().unwrap();
+		let mut statement = connection
+			.prepare("SELECT user, hash, accepted FROM rules_acceptances WHERE guild = ?1 ORDER BY user")?;
+		let acceptances = statement
+			.query_map(params![from_id(guild)], |row| {
+				Ok((to_id(0, row.get(0)?)?, Acceptance::new(row.get(1)?, row.get::<_, i64>(2)? as u64)))
+			})?
+			.collect::<Result<Vec<_>, _>>()?;
+
+		Ok(acceptances)
+	}
+
+	fn clear_acceptances(&self, guild: Id<GuildMarker>) -> Result<(), StateError> {
+		let connection = self.connection.lock().unwrap();
+		connection.execute("DELETE FROM rules_acceptances WHERE guild = ?1", params![from_id(guild)])?;
+
+		Ok(())
+	}
+
+	fn flush(&self) -> Result<(), StateError> {
+		// Every change is committed when it's made, so there's nothing left to write.
+		Ok(())
+	}
+}
+
+fn migrate(connection: &mut Connection, context: &MigrationContext) -> Result<(), StateError> {
+	let version: u32 = connection.pragma_query_value(None, "user_version", |row| row.get(0))?;
+
+	if version as usize > MIGRATIONS.len() {
+		return Err(StateError::UnsupportedVersion(u64::from(version)));
+	}
+
+	for (from, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+		tracing::info!(from, to = from + 1, "Migrating state database.");
+		let transaction = connection.transaction()?;
+		match migration {
+			Migration::Sql(sql) => transaction.execute_batch(sql)?,
+			Migration::Code(f) => f(&transaction, context)?,
+		}
+		transaction.pragma_update(None, "user_version", from + 1)?;
+		transaction.commit()?;
+	}
+
+	Ok(())
+}
+
+// Discord ids are 64 bit snowflakes, but they never use the highest bit,
+// so they fit in an SQLite integer.
+fn from_id<T>(id: Id<T>) -> i64 {
+	id.get() as i64
+}
+
+fn to_id<T>(column: usize, value: i64) -> rusqlite::Result<Id<T>> {
+	u64::try_from(value).ok()
+		.and_then(Id::new_checked)
+		.ok_or(rusqlite::Error::IntegralValueOutOfRange(column, value))
+}
@@ -0,0 +1,78 @@
+use twilight_http::Client;
+
+use async_trait::async_trait;
+
+use tracing::{debug, warn, error};
+
+use std::sync::Arc;
+
+use crate::Context;
+use crate::config::{self, Guild};
+use crate::module::Module;
+use crate::roles;
+use crate::template::Resolver;
+use crate::posted::{self, Changes, SyncError};
+use crate::welcome;
+
+/// Other messages that are kept in sync with a file like the welcome message.
+pub struct StickyModule;
+
+#[async_trait]
+impl Module for StickyModule {
+	fn name(&self) -> &'static str {
+		"sticky"
+	}
+
+	async fn sync(&self, client: &Client, context: &Arc<Context>, guild: &config::Guild) {
+		handle_sticky_messages(client, Arc::clone(context), guild).await;
+	}
+}
+
+/// Makes a sticky message in its channel match the configuration and stores where it is.
+///
+/// Sticky messages are kept in sync like the welcome message, see [`posted::sync_messages`].
+pub async fn sync(client: &Client, context: &Context, guild: &Guild, sticky: &config::Sticky) -> Result<Changes, SyncError> {
+	let (content, embeds) = posted::load(&Resolver::new(client, guild), sticky.source().content(), sticky.source().embeds()).await?;
+	let menus = if context.config().modules().enabled("roles") { sticky.roles() } else { &[] };
+	let posts = welcome::posts(&content, &embeds, roles::components(menus));
+	let kind = format!("sticky message {}", sticky.name());
+	if posts.is_empty() {
+		return Err(SyncError::NotConfigured(kind));
+	}
+
+	let _lock = context.sync.lock().await;
+
+	let state = context.state.sticky(guild.id(), sticky.name())
+		.map_err(SyncError::State)?
+		.unwrap_or_default();
+
+	let changes = posted::sync_messages(client, &kind, sticky.channel(), state.messages(), &posts).await?;
+
+	if changes.messages() != state.messages() {
+		let mut state = state;
+		state.set_messages(changes.messages().to_vec());
+		context.state.set_sticky(guild.id(), sticky.name(), state).map_err(SyncError::State)?;
+	}
+
+	Ok(changes)
+}
+
+/// Syncs every sticky message of the guild, logging what was done.
+// NOTE Sticky messages that are removed from the configuration are left in their channels.
+pub async fn handle_sticky_messages(client: &Client, context: Arc<Context>, guild: &Guild) {
+	for sticky in guild.sticky() {
+		match sync(client, &context, guild, sticky).await {
+			Ok(changes) => debug!(
+				guild = %guild.id(),
+				sticky = sticky.name(),
+				unchanged = changes.unchanged(),
+				edited = changes.edited(),
+				posted = changes.posted(),
+				deleted = changes.deleted(),
+				"Synced sticky message.",
+			),
+			Err(SyncError::NotConfigured(_)) => warn!(guild = %guild.id(), sticky = sticky.name(), "Sticky message is empty."),
+			Err(e) => error!(guild = %guild.id(), sticky = sticky.name(), error = %e, "Couldn't sync sticky message!"),
+		}
+	}
+}
@@ -0,0 +1,306 @@
+use twilight_http::Client;
+use twilight_model::id::Id;
+use twilight_model::id::marker::GuildMarker;
+
+use tokio::sync::OnceCell;
+
+use std::collections::HashMap;
+
+use crate::config;
+
+/// Replaces `{name}` with the value of `name` for each of the placeholders.
+/// Braces around anything else are left as they are.
+pub fn render(template: &str, placeholders: &[(&str, String)]) -> String {
+	let mut rendered = String::from(template);
+	for (name, value) in placeholders {
+		rendered = rendered.replace(&format!("{{{}}}", name), value);
+	}
+	rendered
+}
+
+#[derive(Debug)]
+pub enum TemplateError {
+	/// The placeholders, with braces, whose names aren't in the guild or the configuration.
+	Unresolved(Vec<String>),
+	Fetch(String),
+}
+
+impl std::fmt::Display for TemplateError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			TemplateError::Unresolved(names) => write!(f, "unresolved placeholders {}", names.join(", ")),
+			TemplateError::Fetch(e) => write!(f, "couldn't fetch names from the guild: {}", e),
+		}
+	}
+}
+
+impl std::error::Error for TemplateError {}
+
+/// Resolves placeholders that refer to the guild by name, so that content doesn't have to
+/// hard-code ids that differ between guilds:
+///
+/// - `{role:Styrelsen}` becomes a mention of the role.
+/// - `{channel:regler}` becomes a link to the channel.
+/// - `{emoji:ac}` becomes the custom emoji.
+/// - `{config.member.role}` becomes the value in the configuration of the guild. Roles and
+///   channels are mentioned, other values are inserted as they are.
+///
+/// The guild is only asked for the kinds of names that are used, once per resolver.
+pub struct Resolver<'a> {
+	client: &'a Client,
+	config: &'a config::Guild,
+	roles: OnceCell<HashMap<String, Vec<String>>>,
+	channels: OnceCell<HashMap<String, Vec<String>>>,
+	emojis: OnceCell<HashMap<String, Vec<String>>>,
+}
+
+#[derive(Clone, Copy)]
+enum Kind {
+	Role,
+	Channel,
+	Emoji,
+}
+
+impl<'a> Resolver<'a> {
+	pub fn new(client: &'a Client, config: &'a config::Guild) -> Resolver<'a> {
+		Resolver {
+			client,
+			config,
+			roles: OnceCell::new(),
+			channels: OnceCell::new(),
+			emojis: OnceCell::new(),
+		}
+	}
+
+	/// The guild whose names are resolved.
+	pub fn guild(&self) -> Id<GuildMarker> {
+		self.config.id()
+	}
+
+	/// Replaces the placeholders of the guild in `template`. Other braces are left as they are,
+	/// so this can be used on JSON and together with [`render`].
+	pub async fn render(&self, template: &str) -> Result<String, TemplateError> {
+		let mut rendered = String::with_capacity(template.len());
+		let mut unresolved = Vec::new();
+		let mut rest = template;
+
+		while let Some(start) = rest.find('{') {
+			rendered.push_str(&rest[..start]);
+			let after = &rest[start + 1..];
+
+			let end = match after.find(['{', '}']) {
+				Some(end) if after[end..].starts_with('}') => end,
+				_ => {
+					rendered.push('{');
+					rest = after;
+					continue;
+				},
+			};
+
+			let name = &after[..end];
+			match self.resolve(name).await? {
+				Some(Some(value)) => rendered.push_str(&value),
+				Some(None) => {
+					unresolved.push(format!("{{{}}}", name));
+					rendered.push_str(&rest[start..start + end + 2]);
+				},
+				None => rendered.push_str(&rest[start..start + end + 2]),
+			}
+			rest = &after[end + 1..];
+		}
+		rendered.push_str(rest);
+
+		if unresolved.is_empty() {
+			Ok(rendered)
+		} else {
+			Err(TemplateError::Unresolved(unresolved))
+		}
+	}
+
+	/// Returns `None` if the name isn't one of ours, and `Some(None)` if it can't be resolved.
+	async fn resolve(&self, name: &str) -> Result<Option<Option<String>>, TemplateError> {
+		if let Some(path) = name.strip_prefix("config.") {
+			return Ok(Some(self.config_value(path)));
+		}
+
+		let (kind, name) = match name.split_once(':') {
+			Some(("role", name)) => (Kind::Role, name),
+			Some(("channel", name)) => (Kind::Channel, name),
+			Some(("emoji", name)) => (Kind::Emoji, name),
+			_ => return Ok(None),
+		};
+
+		// A name that is used more than once can't be told apart, so it counts as unresolved.
+		Ok(Some(match self.names(kind).await?.get(name).map(Vec::as_slice) {
+			Some([value]) => Some(value.clone()),
+			_ => None,
+		}))
+	}
+
+	async fn names(&self, kind: Kind) -> Result<&HashMap<String, Vec<String>>, TemplateError> {
+		let guild = self.guild();
+		match kind {
+			Kind::Role => self.roles.get_or_try_init(|| fetch_roles(self.client, guild)).await,
+			Kind::Channel => self.channels.get_or_try_init(|| fetch_channels(self.client, guild)).await,
+			Kind::Emoji => self.emojis.get_or_try_init(|| fetch_emojis(self.client, guild)).await,
+		}
+	}
+
+	fn config_value(&self, path: &str) -> Option<String> {
+		let config = toml::Value::try_from(self.config).ok()?;
+		let value = path.split('.').try_fold(&config, |value, key| value.get(key))?;
+
+		let value = match value {
+			toml::Value::String(s) => s.clone(),
+			toml::Value::Integer(i) => i.to_string(),
+			toml::Value::Float(f) => f.to_string(),
+			toml::Value::Boolean(b) => b.to_string(),
+			_ => return None,
+		};
+
+		// SAFETY The path has at least one key, since the value was found.
+		Some(match path.rsplit('.').next().unwrap() {
+			"role" => format!("<@&{}>", value),
+			"channel" => format!("<#{}>", value),
+			_ => value,
+		})
+	}
+}
+
+fn insert(names: &mut HashMap<String, Vec<String>>, name: String, value: String) {
+	names.entry(name).or_default().push(value);
+}
+
+async fn fetch_roles(client: &Client, guild: Id<GuildMarker>) -> Result<HashMap<String, Vec<String>>, TemplateError> {
+	let roles = client.roles(guild).await
+		.map_err(|e| TemplateError::Fetch(e.to_string()))?
+		.models().await
+		.map_err(|e| TemplateError::Fetch(e.to_string()))?;
+
+	let mut names = HashMap::new();
+	for role in roles {
+		insert(&mut names, role.name, format!("<@&{}>", role.id));
+	}
+	Ok(names)
+}
+
+async fn fetch_channels(client: &Client, guild: Id<GuildMarker>) -> Result<HashMap<String, Vec<String>>, TemplateError> {
+	let channels = client.guild_channels(guild).await
+		.map_err(|e| TemplateError::Fetch(e.to_string()))?
+		.models().await
+		.map_err(|e| TemplateError::Fetch(e.to_string()))?;
+
+	let mut names = HashMap::new();
+	for channel in channels {
+		if let Some(name) = channel.name {
+			insert(&mut names, name, format!("<#{}>", channel.id));
+		}
+	}
+	Ok(names)
+}
+
+async fn fetch_emojis(client: &Client, guild: Id<GuildMarker>) -> Result<HashMap<String, Vec<String>>, TemplateError> {
+	let emojis = client.emojis(guild).await
+		.map_err(|e| TemplateError::Fetch(e.to_string()))?
+		.models().await
+		.map_err(|e| TemplateError::Fetch(e.to_string()))?;
+
+	let mut names = HashMap::new();
+	for emoji in emojis {
+		let prefix = if emoji.animated { "a" } else { "" };
+		insert(&mut names, emoji.name.clone(), format!("<{}:{}:{}>", prefix, emoji.name, emoji.id));
+	}
+	Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn config() -> config::Guild {
+		toml::from_str("id = \"100\"\n\n[welcome]\nchannel = \"500\"\ntext = \"Hej\"").unwrap()
+	}
+
+	fn names(names: &[(&str, &str)]) -> OnceCell<HashMap<String, Vec<String>>> {
+		let mut map = HashMap::new();
+		for (name, value) in names {
+			insert(&mut map, String::from(*name), String::from(*value));
+		}
+		OnceCell::new_with(Some(map))
+	}
+
+	/// Renders `template` with names that are already known, so that the guild is never asked.
+	async fn render_guild(template: &str) -> Result<String, TemplateError> {
+		let client = Client::new(String::new());
+		let config = config();
+		let resolver = Resolver {
+			client: &client,
+			config: &config,
+			roles: names(&[("Styrelsen", "<@&1>"), ("Admin", "<@&2>"), ("Admin", "<@&3>")]),
+			channels: names(&[("regler", "<#4>")]),
+			emojis: names(&[("ac", "<:ac:5>")]),
+		};
+		resolver.render(template).await
+	}
+
+	fn unresolved(result: Result<String, TemplateError>) -> Vec<String> {
+		match result {
+			Err(TemplateError::Unresolved(names)) => names,
+			Err(e) => panic!("unexpected error: {}", e),
+			Ok(rendered) => panic!("rendered as {:?}", rendered),
+		}
+	}
+
+	#[test]
+	fn render_replaces_only_given_placeholders() {
+		let rendered = render("{user} fick {role} men inte {other}", &[("user", String::from("Kalle")), ("role", String::from("<@&1>"))]);
+		assert_eq!(rendered, "Kalle fick <@&1> men inte {other}");
+	}
+
+	#[tokio::test]
+	async fn keeps_text_around_placeholders() {
+		let rendered = render_guild("Hej {role:Styrelsen}, läs {channel:regler} {emoji:ac}!").await.unwrap();
+		assert_eq!(rendered, "Hej <@&1>, läs <#4> <:ac:5>!");
+	}
+
+	#[tokio::test]
+	async fn resolves_config_values() {
+		let rendered = render_guild("Välkommen till {config.welcome.channel}, {config.welcome.text}").await.unwrap();
+		assert_eq!(rendered, "Välkommen till <#500>, Hej");
+	}
+
+	#[tokio::test]
+	async fn leaves_unknown_prefixes() {
+		let template = "{user:Kalle} {role} {} {:ac} {\"json\": true}";
+		assert_eq!(render_guild(template).await.unwrap(), template);
+	}
+
+	#[tokio::test]
+	async fn leaves_unbalanced_braces() {
+		assert_eq!(render_guild("{role:Styrelsen").await.unwrap(), "{role:Styrelsen");
+		assert_eq!(render_guild("} {role:Styrelsen}").await.unwrap(), "} <@&1>");
+		assert_eq!(render_guild("{ {role:Styrelsen}").await.unwrap(), "{ <@&1>");
+	}
+
+	#[tokio::test]
+	async fn resolves_innermost_of_nested_braces() {
+		assert_eq!(render_guild("{{role:Styrelsen}}").await.unwrap(), "{<@&1>}");
+		assert_eq!(render_guild("{role:{emoji:ac}}").await.unwrap(), "{role:<:ac:5>}");
+	}
+
+	#[tokio::test]
+	async fn reports_missing_names() {
+		let result = render_guild("{role:Okänd} {channel:regler} {emoji:saknas} {channel:}").await;
+		assert_eq!(unresolved(result), ["{role:Okänd}", "{emoji:saknas}", "{channel:}"]);
+	}
+
+	#[tokio::test]
+	async fn reports_duplicate_names() {
+		assert_eq!(unresolved(render_guild("{role:Admin}").await), ["{role:Admin}"]);
+	}
+
+	#[tokio::test]
+	async fn reports_missing_config_values() {
+		assert_eq!(unresolved(render_guild("{config.member.role}").await), ["{config.member.role}"]);
+	}
+}
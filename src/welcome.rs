@@ -1,90 +1,680 @@
 use twilight_http::Client;
-use twilight_model::id::{Id, marker::{ChannelMarker, MessageMarker}};
+use twilight_http::client::InteractionClient;
+use twilight_model::id::{Id, marker::ApplicationMarker};
+use twilight_model::application::interaction::{Interaction, InteractionData};
 use twilight_model::channel::Message;
+use twilight_model::channel::message::{Embed, MessageFlags};
+use twilight_model::channel::message::component::{Component, ActionRow, Button, ButtonStyle};
+use twilight_model::http::attachment::Attachment;
+use twilight_model::http::interaction::{InteractionResponse, InteractionResponseType, InteractionResponseData};
+
+use vesper::macros::{command, check};
+use vesper::builder::FrameworkBuilder;
+use vesper::framework::{Framework, DefaultCommandResult, DefaultError};
+use vesper::context::SlashContext;
+
+use async_trait::async_trait;
+
+use tracing::{debug, info, warn, error};
 
 use std::sync::Arc;
-use crate::Context;
-
-pub enum WelcomeError {
-	MessageNotFound,
-	WrongContent,
-	Other,
-}
-
-// NOTE This requires the SEND_MESSAGES permission.
-pub async fn post_welcome_message<M: AsRef<str>>(client: &Client, channel: Id<ChannelMarker>, content: M) -> Message {
-	client
-		.create_message(channel)
-		.content(content.as_ref()).expect("Message was malformed.")
-		.await.expect("Couldn't send welcome message.")
-		.model().await.expect("Couldn't deserialize message from response.")
-}
-
-// NOTE This doesn't require any permissions, since we only edit our own messages.
-pub async fn edit_welcome_message<M: AsRef<str>>(client: &Client, channel: Id<ChannelMarker>, message: Id<MessageMarker>, content: M) {
-	client
-		.update_message(channel, message)
-		.content(Some(content.as_ref())).expect("Message was malformed.")
-		.await.expect("Couldn't update welcome message.")
-		.model().await.expect("Couldn't deserialize message from response.");
-}
-
-pub async fn validate_welcome_message<M: AsRef<str>>(
-	client: &Client,
-	channel: Id<ChannelMarker>,
-	message: Id<MessageMarker>,
-	content: M
-) -> Result<(), WelcomeError> {
-	let response = match client.message(channel, message).await {
-		Ok(response) => response,
-		Err(e) => return Err(match e.kind() {
-			twilight_http::error::ErrorType::Response { .. } => WelcomeError::MessageNotFound,
-			_ => WelcomeError::Other,
-		})
+use crate::{Context, guild_config, has_permission};
+use crate::config::{self, Guild};
+use crate::embed::{self, LoadedEmbed};
+use crate::module::Module;
+use crate::roles;
+use crate::rules;
+use crate::posted::{self, Changes, SyncError};
+use crate::template::Resolver;
+
+/// The maximum number of characters Discord allows in the content of a message.
+pub const MESSAGE_LIMIT: usize = 2000;
+
+/// A line containing only this starts a new message in the welcome file.
+pub const SEPARATOR: &str = "---";
+
+/// What the welcome message is called in the logs.
+const KIND: &str = "welcome message";
+
+/// The start of the custom id of the buttons that show a translation, followed by the locale.
+const TRANSLATION_PREFIX: &str = "welcome:";
+
+/// The welcome message, its translations and the `/welcome` commands.
+pub struct WelcomeModule;
+
+#[async_trait]
+impl Module for WelcomeModule {
+	fn name(&self) -> &'static str {
+		"welcome"
+	}
+
+	fn commands(&self, framework: FrameworkBuilder<Arc<Context>>) -> FrameworkBuilder<Arc<Context>> {
+		framework.group(|g| g
+			.name("welcome")
+			.description("The welcome message")
+			.command(welcome_preview)
+			.command(welcome_sync)
+			.command(welcome_repost))
+	}
+
+	async fn sync(&self, client: &Client, context: &Arc<Context>, guild: &Guild) {
+		handle_welcome_message(client, Arc::clone(context), guild).await;
+	}
+
+	fn handles_component(&self, custom_id: &str) -> bool {
+		is_translation(custom_id)
+	}
+
+	async fn component(&self, framework: &Framework<Arc<Context>>, interaction: Interaction) {
+		handle_translation(framework.http_client(), framework.application_id, Arc::clone(&framework.data), interaction).await;
+	}
+}
+
+/// One of the messages that a welcome or sticky message is posted as.
+pub struct Post {
+	content: String,
+	embeds: Vec<LoadedEmbed>,
+	components: Vec<Component>,
+}
+
+/// Splits the text and embeds of the welcome message into the messages it is posted as.
+///
+/// The embeds are added to the last message of text, and to new messages after it
+/// if they don't fit in one message. The components are added to the last message.
+pub fn posts(content: &str, embeds: &[LoadedEmbed], components: Vec<Component>) -> Vec<Post> {
+	let mut posts: Vec<Post> = split(content).into_iter()
+		.map(Post::new)
+		.collect();
+
+	if posts.is_empty() && !embeds.is_empty() {
+		posts.push(Post::new(String::new()));
+	}
+
+	for e in embeds {
+		// SAFETY posts isn't empty if there are embeds.
+		let last = posts.last().unwrap();
+		let length = last.embeds.iter().map(LoadedEmbed::length).sum::<usize>() + e.length();
+		if last.embeds.len() == embed::EMBEDS_PER_MESSAGE || length > embed::TOTAL_LENGTH {
+			posts.push(Post::new(String::new()));
+		}
+		// SAFETY See above.
+		posts.last_mut().unwrap().embeds.push(e.clone());
+	}
+
+	if let Some(last) = posts.last_mut() {
+		last.components = components;
+	}
+
+	posts
+}
+
+/// Splits the text of the welcome message into the messages it is posted as.
+///
+/// The content is first split at [`SEPARATOR`] lines. Parts that are still too long
+/// are split at paragraphs, then at lines, and as a last resort anywhere.
+pub fn split(content: &str) -> Vec<String> {
+	let mut sections = vec![String::new()];
+	for line in content.lines() {
+		if line.trim() == SEPARATOR {
+			sections.push(String::new());
+		} else {
+			// SAFETY sections always has at least one element.
+			let section = sections.last_mut().unwrap();
+			section.push_str(line);
+			section.push('\n');
+		}
+	}
+
+	let mut messages = Vec::new();
+	for section in &sections {
+		pack(section.trim(), &["\n\n", "\n"], &mut messages);
+	}
+
+	// Discord trims the content of messages, so they are compared trimmed when synced.
+	messages.into_iter()
+		.map(|m| String::from(m.trim()))
+		.filter(|m| !m.is_empty())
+		.collect()
+}
+
+/// Adds `text` to `messages`, split at the first of `delimiters` that makes the parts fit.
+fn pack(text: &str, delimiters: &[&str], messages: &mut Vec<String>) {
+	if text.chars().count() <= MESSAGE_LIMIT {
+		messages.push(String::from(text));
+		return;
+	}
+
+	let (delimiter, rest) = match delimiters.split_first() {
+		Some(d) => d,
+		None => {
+			let chars: Vec<char> = text.chars().collect();
+			messages.extend(chars.chunks(MESSAGE_LIMIT).map(|c| c.iter().collect::<String>()));
+			return;
+		},
 	};
 
-	let message = response.model().await
-		.expect("Couldn't deserialize message from response.");
+	let mut current = String::new();
+	// Whitespace in the parts is kept, since it may be the indentation of a code block or list.
+	for part in text.split(delimiter).filter(|p| !p.is_empty()) {
+		let length = current.chars().count() + delimiter.len() + part.chars().count();
+		if !current.is_empty() && length > MESSAGE_LIMIT {
+			messages.push(std::mem::take(&mut current));
+		}
 
-	if message.content != content.as_ref() {
-		return Err(WelcomeError::WrongContent);
+		if part.chars().count() > MESSAGE_LIMIT {
+			pack(part, rest, messages);
+		} else {
+			if !current.is_empty() {
+				current.push_str(delimiter);
+			}
+			current.push_str(part);
+		}
 	}
 
-	Ok(())
+	if !current.is_empty() {
+		messages.push(current);
+	}
+}
+
+impl Post {
+	fn new(content: String) -> Post {
+		Post {
+			content,
+			embeds: Vec::new(),
+			components: Vec::new(),
+		}
+	}
+
+	pub fn content(&self) -> &str {
+		&self.content
+	}
+
+	pub fn embeds(&self) -> Vec<Embed> {
+		self.embeds.iter().map(|e| e.embed().clone()).collect()
+	}
+
+	pub fn components(&self) -> &[Component] {
+		&self.components
+	}
+
+	/// The local files shown in the embeds, each uploaded once.
+	pub fn files(&self) -> Vec<std::path::PathBuf> {
+		let mut files: Vec<_> = self.embeds.iter().flat_map(|e| e.files().iter().cloned()).collect();
+		files.sort();
+		files.dedup();
+		files
+	}
+
+	/// Whether a posted message shows the same thing as this one.
+	pub fn matches(&self, message: &Message) -> bool {
+		message.content == self.content
+			&& message.embeds.len() == self.embeds.len()
+			&& self.embeds.iter().zip(&message.embeds).all(|(e, posted)| e.matches(posted))
+			&& components_match(&self.components, &message.components)
+	}
+}
+
+/// Whether posted components are the ones the bot sent.
+///
+/// Discord fills in defaults and normalizes fields like emojis when it sends components back,
+/// so only the fields that the bot sets and that change what is shown are compared.
+fn components_match(ours: &[Component], posted: &[Component]) -> bool {
+	ours.len() == posted.len() && ours.iter().zip(posted).all(|(ours, posted)| match (ours, posted) {
+		(Component::ActionRow(ours), Component::ActionRow(posted)) => components_match(&ours.components, &posted.components),
+		(Component::Button(ours), Component::Button(posted)) => ours.custom_id == posted.custom_id
+			&& ours.label == posted.label
+			&& ours.style == posted.style,
+		(Component::SelectMenu(ours), Component::SelectMenu(posted)) => ours.custom_id == posted.custom_id
+			&& ours.max_values == posted.max_values
+			&& ours.placeholder == posted.placeholder
+			&& ours.options.len() == posted.options.len()
+			&& ours.options.iter().zip(&posted.options).all(|(ours, posted)| ours.value == posted.value
+				&& ours.label == posted.label
+				&& ours.description == posted.description),
+		_ => false,
+	})
+}
+
+/// Builds the row with a button for each translation of the welcome message.
+pub fn translation_buttons(welcome: &config::Welcome) -> Option<Component> {
+	if welcome.translations().is_empty() {
+		return None;
+	}
+
+	let buttons = welcome.translations().iter().map(|(locale, translation)| Component::Button(Button {
+		custom_id: Some(format!("{}{}", TRANSLATION_PREFIX, locale)),
+		disabled: false,
+		emoji: None,
+		label: Some(String::from(translation.label())),
+		style: ButtonStyle::Secondary,
+		url: None,
+	})).collect();
+
+	Some(Component::ActionRow(ActionRow {
+		components: buttons,
+	}))
+}
+
+/// Whether the custom id of a component belongs to a translation button.
+pub fn is_translation(custom_id: &str) -> bool {
+	custom_id.starts_with(TRANSLATION_PREFIX)
 }
 
-pub async fn handle_welcome_message(client: &Client, context: Arc<Context>) {
-    let channel = context.config.welcome().channel();
-	let content = match context.config.welcome().content() {
-		Some(content) => content,
-		None => return, // There is no message to handle.
+/// Shows a translation of the welcome message to the member who clicked its button.
+///
+/// The translation is read when it's shown, so it's always the current version.
+pub async fn handle_translation(client: &Client, application: Id<ApplicationMarker>, context: Arc<Context>, interaction: Interaction) {
+	let interaction_client = client.interaction(application);
+
+	// SAFETY The handler is only called for message components with the prefix.
+	let locale = match &interaction.data {
+		Some(InteractionData::MessageComponent(data)) => data.custom_id.strip_prefix(TRANSLATION_PREFIX).unwrap(),
+		_ => unreachable!(),
 	};
 
-	let state_path = &context.state_path;
-	let state = &mut context.state.write().unwrap();
-
-	if let Some(welcome) = state.welcome_mut() {
-		match validate_welcome_message(&client, channel, welcome.message(), &content).await {
-			Ok(_) => return,
-			Err(e) => match e {
-				WelcomeError::MessageNotFound => {
-					let message = post_welcome_message(&client, channel, content).await;
-					welcome.set_message(message.id);
-					if let Err(_) = crate::state::to_file(state_path, state) {
-						eprintln!("Couldn't write state to file!");
-					}
-				},
-				WelcomeError::WrongContent => {
-					edit_welcome_message(&client, channel, welcome.message(), content).await;
+	let config = interaction.guild_id.and_then(|guild| context.config().guild(guild));
+	let translation = config.as_ref()
+		.and_then(|c| Some((c, c.welcome()?.translations().get(locale)?)));
+
+	let loaded = match translation {
+		Some((config, t)) => Some(posted::load(&Resolver::new(client, config), t.source().content(), t.source().embeds()).await),
+		None => None,
+	};
+
+	let posts = match loaded {
+		Some(Ok((content, embeds))) => {
+			let posts = posts(&content, &embeds, Vec::new());
+			match with_attachments(posts) {
+				Ok(posts) => posts,
+				Err(e) => {
+					error!(locale, error = %e, "Couldn't read welcome translation attachments!");
+					Vec::new()
 				},
-				WelcomeError::Other => return,
 			}
+		},
+		Some(Err(e)) => {
+			error!(locale, error = %e, "Couldn't read welcome translation!");
+			Vec::new()
+		},
+		None => {
+			warn!(locale, "Welcome translation is no longer configured.");
+			Vec::new()
+		},
+	};
+
+	let ((first, attachments), rest) = match posts.split_first() {
+		Some(split) => split,
+		None => {
+			let response = InteractionResponse {
+				kind: InteractionResponseType::ChannelMessageWithSource,
+				data: Some(InteractionResponseData {
+					content: Some(context.text(interaction.locale.as_deref(), "welcome.translation_unavailable", &[])),
+					flags: Some(MessageFlags::EPHEMERAL),
+					..Default::default()
+				}),
+			};
+			if let Err(e) = interaction_client.create_response(interaction.id, &interaction.token, &response).await {
+				error!(error = %e, "Couldn't respond to translation button.");
+			}
+			return;
+		},
+	};
+
+	let response = InteractionResponse {
+		kind: InteractionResponseType::ChannelMessageWithSource,
+		data: Some(InteractionResponseData {
+			content: Some(first.content.clone()).filter(|c| !c.is_empty()),
+			embeds: Some(first.embeds()),
+			attachments: Some(attachments.clone()),
+			flags: Some(MessageFlags::EPHEMERAL),
+			..Default::default()
+		}),
+	};
+
+	debug!(locale, messages = posts.len(), "Showing welcome translation.");
+	if let Err(e) = interaction_client.create_response(interaction.id, &interaction.token, &response).await {
+		error!(error = %e, "Couldn't respond to translation button.");
+		return;
+	}
+
+	// The rest of a long translation follows in more messages that only the member can see.
+	send_followups(&interaction_client, &interaction.token, rest).await;
+}
+
+/// Reads the files to upload with each of the posts, so that a missing file is found
+/// before anything is sent.
+fn with_attachments(posts: Vec<Post>) -> Result<Vec<(Post, Vec<Attachment>)>, embed::EmbedError> {
+	posts.into_iter()
+		.map(|post| {
+			let attachments = embed::attachments(&post.files())?;
+			Ok((post, attachments))
+		})
+		.collect()
+}
+
+/// Sends posts as followups to an interaction, visible only to the user who started it.
+async fn send_followups(interaction_client: &InteractionClient<'_>, token: &str, posts: &[(Post, Vec<Attachment>)]) {
+	for (post, attachments) in posts {
+		let embeds = post.embeds();
+		let mut request = interaction_client.create_followup(token)
+			.embeds(&embeds).expect("Message was malformed.")
+			.attachments(attachments).expect("Message was malformed.")
+			.flags(MessageFlags::EPHEMERAL);
+		if !post.content().is_empty() {
+			request = request.content(post.content()).expect("Message was malformed.");
 		}
-	} else {
-		let message = post_welcome_message(&client, channel, &content).await;
-		state.set_welcome(crate::state::Welcome::new(message.id));
-		if let Err(_) = crate::state::to_file(state_path, state) {
-			eprintln!("Couldn't write state to file!");
+
+		if let Err(e) = request.await {
+			error!(error = %e, "Couldn't send followup with welcome message.");
+			return;
 		}
 	}
-}
\ No newline at end of file
+}
+
+/// Builds the messages that make up the configured welcome message, with the components
+/// of the enabled modules.
+pub async fn welcome_posts(resolver: &Resolver<'_>, config: &config::Welcome, modules: &config::Modules) -> Result<Vec<Post>, SyncError> {
+	let (content, embeds) = posted::load(resolver, config.source().content(), config.source().embeds()).await?;
+
+	let mut components = Vec::new();
+	if modules.enabled("roles") {
+		components.extend(roles::components(config.roles()));
+	}
+	components.extend(translation_buttons(config));
+	if modules.enabled("rules") {
+		components.extend(config.rules().map(rules::components));
+	}
+
+	Ok(posts(&content, &embeds, components))
+}
+
+/// Makes the welcome message in the channel match the configuration and stores where it is.
+///
+/// With `repost`, the welcome message is posted again at the bottom of the channel and the
+/// old messages are deleted, instead of being edited in place. The old messages are only
+/// deleted once the new ones are posted and stored.
+pub async fn sync(client: &Client, context: &Context, guild: &Guild, repost: bool) -> Result<Changes, SyncError> {
+	let config = guild.welcome().ok_or_else(|| SyncError::NotConfigured(String::from(KIND)))?;
+	let posts = welcome_posts(&Resolver::new(client, guild), config, context.config().modules()).await?;
+	if posts.is_empty() {
+		return Err(SyncError::NotConfigured(String::from(KIND)));
+	}
+
+	// Two syncs at the same time would both post the missing messages.
+	let _lock = context.sync.lock().await;
+
+	let mut welcome = context.state.welcome(guild.id())
+		.map_err(SyncError::State)?
+		.unwrap_or_default();
+
+	if !repost {
+		let changes = posted::sync_messages(client, KIND, config.channel(), welcome.messages(), &posts).await?;
+		if changes.messages() != welcome.messages() {
+			welcome.set_messages(changes.messages().to_vec());
+			context.state.set_welcome(guild.id(), welcome).map_err(SyncError::State)?;
+		}
+		return Ok(changes);
+	}
+
+	info!(guild = %guild.id(), "Reposting welcome message.");
+	let mut changes = posted::sync_messages(client, KIND, config.channel(), &[], &posts).await?;
+	let old = welcome.messages().to_vec();
+	welcome.set_messages(changes.messages().to_vec());
+	context.state.set_welcome(guild.id(), welcome).map_err(SyncError::State)?;
+
+	for message in old {
+		match posted::delete_message(client, KIND, config.channel(), message).await {
+			Ok(_) => changes.add_deleted(),
+			// The new messages are already stored, so the old one has to be deleted by hand.
+			Err(e) => error!(%message, error = %e, "Couldn't delete old welcome message!"),
+		}
+	}
+
+	Ok(changes)
+}
+
+/// Shows the configured welcome message to the user who used the command, without posting it.
+///
+/// The first response says how it would be posted, and the messages follow. The components
+/// aren't shown, since using them would give real roles.
+pub async fn preview(client: &Client, application: Id<ApplicationMarker>, context: &Context, guild: &Guild, interaction: &Interaction) {
+	let interaction_client = client.interaction(application);
+	let text = |key, placeholders: &[(&str, String)]| context.text(interaction.locale.as_deref(), key, placeholders);
+
+	let posts = match guild.welcome() {
+		Some(config) => welcome_posts(&Resolver::new(client, guild), config, context.config().modules()).await
+			.and_then(|posts| with_attachments(posts).map_err(SyncError::Embeds))
+			.map(|posts| (config, posts)),
+		None => Err(SyncError::NotConfigured(String::from(KIND))),
+	};
+	let (content, posts) = match posts {
+		Ok((config, posts)) if !posts.is_empty() => {
+			let rows: usize = posts.iter().map(|(p, _)| p.components.len()).sum();
+			let content = text("welcome.preview", &[
+				("messages", posts.len().to_string()),
+				("channel", format!("<#{}>", config.channel())),
+				("rows", rows.to_string()),
+			]);
+			(content, posts)
+		},
+		Ok(_) | Err(SyncError::NotConfigured(_)) => (text("welcome.not_configured", &[]), Vec::new()),
+		Err(e) => {
+			error!(error = %e, "Couldn't preview welcome message.");
+			(text("welcome.failed", &[("error", e.to_string())]), Vec::new())
+		},
+	};
+
+	let response = InteractionResponse {
+		kind: InteractionResponseType::ChannelMessageWithSource,
+		data: Some(InteractionResponseData {
+			content: Some(content),
+			flags: Some(MessageFlags::EPHEMERAL),
+			..Default::default()
+		}),
+	};
+
+	if let Err(e) = interaction_client.create_response(interaction.id, &interaction.token, &response).await {
+		error!(error = %e, "Couldn't respond to command.");
+		return;
+	}
+
+	send_followups(&interaction_client, &interaction.token, &posts).await;
+}
+
+pub async fn handle_welcome_message(client: &Client, context: Arc<Context>, guild: &Guild) {
+	match sync(client, &context, guild, false).await {
+		Ok(changes) => debug!(
+			guild = %guild.id(),
+			unchanged = changes.unchanged(),
+			edited = changes.edited(),
+			posted = changes.posted(),
+			deleted = changes.deleted(),
+			"Synced welcome message.",
+		),
+		Err(SyncError::NotConfigured(_)) => debug!(guild = %guild.id(), "No welcome message is configured."),
+		Err(e) => error!(guild = %guild.id(), error = %e, "Couldn't sync welcome message!"),
+	}
+}
+
+#[check]
+async fn welcome_manage_permission(ctx: &SlashContext<Arc<Context>>) -> Result<bool, DefaultError> {
+	let config = match guild_config(ctx).await {
+		Some(config) => config,
+		None => return Ok(false),
+	};
+
+	let welcome = match config.welcome() {
+		Some(welcome) => welcome,
+		None => {
+			let response = InteractionResponse {
+				kind: InteractionResponseType::ChannelMessageWithSource,
+				data: Some(InteractionResponseData {
+					content: Some(ctx.data.text(ctx.interaction.locale.as_deref(), "welcome.not_configured", &[])),
+					flags: Some(MessageFlags::EPHEMERAL),
+					..Default::default()
+				}),
+			};
+
+			let r = ctx.interaction_client.create_response(ctx.interaction.id, &ctx.interaction.token, &response).await;
+			if let Err(e) = r {
+				error!(error = %e, "Couldn't respond to command.");
+			}
+			return Ok(false);
+		},
+	};
+
+	Ok(has_permission(ctx, &config, welcome.permission().manage()).await)
+}
+
+#[command(chat, name = "preview")]
+#[description = "Show the welcome message without posting it"]
+#[checks(welcome_manage_permission)]
+async fn welcome_preview(ctx: &mut SlashContext<Arc<Context>>) -> DefaultCommandResult {
+	let config = match guild_config(ctx).await {
+		Some(config) => config,
+		None => return Ok(()),
+	};
+
+	preview(ctx.http_client(), ctx.application_id, ctx.data, &config, &ctx.interaction).await;
+
+	Ok(())
+}
+
+#[command(chat, name = "sync")]
+#[description = "Update the posted welcome message to match the configuration"]
+#[checks(welcome_manage_permission)]
+async fn welcome_sync(ctx: &mut SlashContext<Arc<Context>>) -> DefaultCommandResult {
+	sync_welcome(ctx, false).await
+}
+
+#[command(chat, name = "repost")]
+#[description = "Delete the welcome message and post it again at the bottom of the channel"]
+#[checks(welcome_manage_permission)]
+async fn welcome_repost(ctx: &mut SlashContext<Arc<Context>>) -> DefaultCommandResult {
+	sync_welcome(ctx, true).await
+}
+
+/// Runs `/welcome sync` or `/welcome repost` and tells the user what was changed.
+async fn sync_welcome(ctx: &mut SlashContext<'_, Arc<Context>>, repost: bool) -> DefaultCommandResult {
+	let config = match guild_config(ctx).await {
+		Some(config) => config,
+		None => return Ok(()),
+	};
+
+	// Posting a long welcome message may take longer than Discord waits for a response.
+	let response = InteractionResponse {
+		kind: InteractionResponseType::DeferredChannelMessageWithSource,
+		data: Some(InteractionResponseData {
+			flags: Some(MessageFlags::EPHEMERAL),
+			..Default::default()
+		}),
+	};
+
+	let r = ctx.interaction_client.create_response(ctx.interaction.id, &ctx.interaction.token, &response).await;
+	if let Err(e) = r {
+		error!(error = %e, "Couldn't respond to command.");
+	}
+
+	let locale = ctx.interaction.locale.clone();
+	let locale = locale.as_deref();
+
+	let content = match sync(ctx.http_client(), ctx.data, &config, repost).await {
+		Ok(changes) => ctx.data.text(locale, "welcome.synced", &[
+			("messages", changes.messages().len().to_string()),
+			("unchanged", changes.unchanged().to_string()),
+			("edited", changes.edited().to_string()),
+			("posted", changes.posted().to_string()),
+			("deleted", changes.deleted().to_string()),
+		]),
+		Err(SyncError::NotConfigured(_)) => ctx.data.text(locale, "welcome.not_configured", &[]),
+		Err(e) => {
+			error!(error = %e, "Couldn't sync welcome message!");
+			ctx.data.text(locale, "welcome.failed", &[("error", e.to_string())])
+		},
+	};
+
+	let r = ctx.interaction_client.update_response(&ctx.interaction.token)
+		.content(Some(&content)).expect("Response content was malformed.")
+		.await;
+	if let Err(e) = r {
+		error!(error = %e, "Couldn't update response to command.");
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn paragraph(c: char, length: usize) -> String {
+		c.to_string().repeat(length)
+	}
+
+	#[test]
+	fn short_text_is_one_message() {
+		assert_eq!(split("Hello\n\nWorld\n"), ["Hello\n\nWorld"]);
+	}
+
+	#[test]
+	fn splits_at_separator() {
+		assert_eq!(split("a\n---\nb\n  ---  \nc"), ["a", "b", "c"]);
+		assert_eq!(split("---\na\n---\n---\n"), ["a"]);
+	}
+
+	#[test]
+	fn splits_long_text_at_paragraphs() {
+		let text = [paragraph('a', 900), paragraph('b', 900), paragraph('c', 900)].join("\n\n");
+		let messages = split(&text);
+
+		assert_eq!(messages, [
+			format!("{}\n\n{}", paragraph('a', 900), paragraph('b', 900)),
+			paragraph('c', 900),
+		]);
+	}
+
+	#[test]
+	fn falls_back_to_lines_and_characters() {
+		let lines = [paragraph('a', 1500), paragraph('b', 1500)].join("\n");
+		assert_eq!(split(&lines), [paragraph('a', 1500), paragraph('b', 1500)]);
+
+		let messages = split(&paragraph('a', 4500));
+		assert_eq!(messages.iter().map(|m| m.chars().count()).collect::<Vec<_>>(), [2000, 2000, 500]);
+	}
+
+	#[test]
+	fn components_match_ignores_defaults() {
+		let button = |label: &str| Component::ActionRow(ActionRow {
+			components: vec![Component::Button(Button {
+				custom_id: Some(String::from("rules:accept")),
+				disabled: false,
+				emoji: None,
+				label: Some(String::from(label)),
+				style: ButtonStyle::Success,
+				url: None,
+			})],
+		});
+
+		let mut posted = button("Accept");
+		if let Component::ActionRow(row) = &mut posted {
+			if let Component::Button(b) = &mut row.components[0] {
+				b.emoji = Some(twilight_model::channel::message::ReactionType::Unicode { name: String::from("✅") });
+			}
+		}
+
+		assert!(components_match(&[button("Accept")], &[posted]));
+		assert!(!components_match(&[button("Accept")], &[button("Decline")]));
+		assert!(!components_match(&[button("Accept")], &[]));
+	}
+
+	#[test]
+	fn keeps_indentation_when_splitting() {
+		let code = "```\nfn main() {\n    println!();\n}\n```";
+		let text = [paragraph('a', 1990), String::from(code), String::from("  - indented")].join("\n\n");
+		let messages = split(&text);
+
+		assert_eq!(messages.len(), 2);
+		assert!(messages.iter().all(|m| m.chars().count() <= MESSAGE_LIMIT));
+		assert_eq!(messages[1], format!("{}\n\n  - indented", code));
+	}
+}
@@ -0,0 +1,67 @@
+use axum::http::{Method, StatusCode};
+
+use twilight_model::id::Id;
+
+mod harness;
+
+use harness::*;
+use kodbot::state::Posted;
+use kodbot::welcome;
+
+const CONFIG: &str = r#"
+[guilds.welcome]
+channel = "500"
+text = "Hej"
+
+[guilds.ebas]
+url = "{ebas}"
+
+[guilds.member]
+role = "400"
+permission = { purge = [] }
+"#;
+
+#[tokio::test]
+async fn welcome_message_is_not_posted() {
+	let fake = FakeDiscord::start().await;
+	let bot = bot_with_client(&fake, CONFIG, fake.dry_run_client()).await;
+
+	let guild = bot.context().config().guild(Id::new(GUILD)).unwrap();
+	let changes = welcome::sync(bot.client(), bot.context(), &guild, false).await.unwrap();
+
+	assert_eq!(changes.posted(), 1);
+	assert!(fake.requests().is_empty());
+}
+
+#[tokio::test]
+async fn welcome_message_is_read_but_not_edited() {
+	let fake = FakeDiscord::start().await;
+	let bot = bot_with_client(&fake, CONFIG, fake.dry_run_client()).await;
+	bot.context().state().set_welcome(Id::new(GUILD), Posted::new(vec![Id::new(600)])).unwrap();
+	fake.respond(Method::GET, "/channels/500/messages/600", StatusCode::OK, message(600, WELCOME_CHANNEL, "Gammal"));
+
+	let guild = bot.context().config().guild(Id::new(GUILD)).unwrap();
+	let changes = welcome::sync(bot.client(), bot.context(), &guild, false).await.unwrap();
+
+	assert_eq!(changes.edited(), 1);
+	assert_eq!(fake.requests_to(Method::GET, "/channels/500/messages/600").len(), 1);
+	assert!(fake.requests_to(Method::PATCH, "/channels/500/messages/600").is_empty());
+}
+
+#[cfg(feature = "member")]
+#[tokio::test]
+async fn command_is_answered_without_giving_the_role() {
+	let fake = FakeDiscord::start().await;
+	let bot = bot_with_client(&fake, CONFIG, fake.dry_run_client()).await;
+	fake.respond(Method::POST, "/ebas/confirm_membership.json", StatusCode::OK, serde_json::json!({
+		"response": {
+			"request_result": { "error": null },
+			"member_found": true,
+		},
+	}));
+
+	inject(&bot, command(1, 10, &[], "member", "verify", &[("email", "medlem@example.com")])).await.unwrap();
+
+	assert!(fake.requests_to(Method::PUT, "/guilds/100/members/10/roles/400").is_empty());
+	assert_eq!(fake.requests_to(Method::POST, "/interactions/1/token1/callback").len(), 1);
+}
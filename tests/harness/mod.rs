@@ -0,0 +1,325 @@
+// Each test file uses a different part of the harness.
+#![allow(dead_code)]
+
+use axum::Router;
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::{Method, StatusCode, Uri};
+use axum::response::{IntoResponse, Response};
+
+use twilight_http::Client;
+use twilight_gateway::Event;
+use twilight_model::application::interaction::Interaction;
+use twilight_model::gateway::payload::incoming::InteractionCreate;
+
+use serde_json::{json, Value};
+
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use kodbot::{config, secrets, state, Bot};
+
+pub const GUILD: u64 = 100;
+pub const APPLICATION: u64 = 200;
+pub const BOT: u64 = 300;
+pub const MEMBER_ROLE: u64 = 400;
+pub const ADMIN_ROLE: u64 = 401;
+pub const WELCOME_CHANNEL: u64 = 500;
+
+/// A request that the bot sent to the fake API.
+#[derive(Clone, Debug)]
+pub struct Request {
+	pub method: Method,
+	/// The path after the API version, without the query, e.g. `/channels/1/messages`.
+	pub path: String,
+	pub body: String,
+}
+
+impl Request {
+	/// The body parsed as JSON, or `Null` if it isn't, e.g. for multipart bodies.
+	pub fn json(&self) -> Value {
+		serde_json::from_str(&self.body).unwrap_or(Value::Null)
+	}
+}
+
+struct Scripted {
+	method: Method,
+	path: String,
+	status: StatusCode,
+	body: Value,
+}
+
+#[derive(Default)]
+struct Shared {
+	requests: Mutex<Vec<Request>>,
+	responses: Mutex<Vec<Scripted>>,
+	// Answered once each, in the order they were added, before the responses above.
+	queued: Mutex<Vec<Scripted>>,
+}
+
+/// A local stand-in for the Discord API, and for eBas, that records every request and
+/// answers with scripted responses. Requests without a script get an empty 204 response.
+pub struct FakeDiscord {
+	address: SocketAddr,
+	shared: Arc<Shared>,
+}
+
+impl FakeDiscord {
+	pub async fn start() -> FakeDiscord {
+		let shared = Arc::new(Shared::default());
+		let app = Router::new()
+			.fallback(handle)
+			.with_state(Arc::clone(&shared));
+
+		let server = axum::Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0)))
+			.serve(app.into_make_service());
+		let address = server.local_addr();
+		tokio::spawn(server);
+
+		FakeDiscord {
+			address,
+			shared,
+		}
+	}
+
+	/// A client for the fake API, without the rate limiter since there are no limits to keep.
+	pub fn client(&self) -> Arc<Client> {
+		Arc::new(Client::builder()
+			.token(String::from("Bot test"))
+			.proxy(self.address.to_string(), true)
+			.ratelimiter(None)
+			.build())
+	}
+
+	/// A client that goes through the dry run proxy of the bot, with the fake API in place of Discord.
+	pub fn dry_run_client(&self) -> Arc<Client> {
+		let upstream = format!("http://{}", self.address);
+		let proxy = kodbot::dry_run::start(twilight_model::id::Id::new(APPLICATION), &upstream).expect("Couldn't start dry run.");
+
+		Arc::new(Client::builder()
+			.token(String::from("Bot test"))
+			.proxy(proxy.to_string(), true)
+			.ratelimiter(None)
+			.build())
+	}
+
+	/// The base URL for eBas, so that membership requests also go to the fake.
+	pub fn ebas_url(&self) -> String {
+		format!("http://{}/ebas", self.address)
+	}
+
+	/// Answers requests with the method and path with `body`. Later scripts take precedence.
+	pub fn respond(&self, method: Method, path: &str, status: StatusCode, body: Value) {
+		self.shared.responses.lock().unwrap().push(Scripted {
+			method,
+			path: String::from(path),
+			status,
+			body,
+		});
+	}
+
+	/// Answers the next request with the method and path with `body`, once. Queued responses
+	/// for the same route are used in the order they were added, and then [`Self::respond`] applies.
+	pub fn respond_once(&self, method: Method, path: &str, status: StatusCode, body: Value) {
+		self.shared.queued.lock().unwrap().push(Scripted {
+			method,
+			path: String::from(path),
+			status,
+			body,
+		});
+	}
+
+	pub fn requests(&self) -> Vec<Request> {
+		self.shared.requests.lock().unwrap().clone()
+	}
+
+	pub fn requests_to(&self, method: Method, path: &str) -> Vec<Request> {
+		self.requests().into_iter().filter(|r| r.method == method && r.path == path).collect()
+	}
+
+	/// Waits until the bot has sent `count` requests with the method and path.
+	pub async fn wait_for(&self, method: Method, path: &str, count: usize) -> Vec<Request> {
+		for _ in 0..500 {
+			let requests = self.requests_to(method.clone(), path);
+			if requests.len() >= count {
+				return requests;
+			}
+			tokio::time::sleep(Duration::from_millis(10)).await;
+		}
+		panic!("Timed out waiting for {} {} {}, got {:#?}", count, method, path, self.requests());
+	}
+}
+
+async fn handle(State(shared): State<Arc<Shared>>, method: Method, uri: Uri, body: Bytes) -> Response {
+	let path = uri.path().strip_prefix("/api/v10").unwrap_or(uri.path());
+	let request = Request {
+		method: method.clone(),
+		path: String::from(path),
+		body: String::from_utf8_lossy(&body).into_owned(),
+	};
+	shared.requests.lock().unwrap().push(request);
+
+	let mut queued = shared.queued.lock().unwrap();
+	if let Some(i) = queued.iter().position(|r| r.method == method && r.path == path) {
+		let r = queued.remove(i);
+		return (r.status, axum::Json(r.body)).into_response();
+	}
+
+	let responses = shared.responses.lock().unwrap();
+	match responses.iter().rev().find(|r| r.method == method && r.path == path) {
+		Some(r) => (r.status, axum::Json(r.body.clone())).into_response(),
+		None => StatusCode::NO_CONTENT.into_response(),
+	}
+}
+
+/// Sets up the bot with the configuration in `guild`, which is a `[[guilds]]` entry
+/// without the id, using the fake API and a state in memory.
+pub async fn bot(fake: &FakeDiscord, guild: &str) -> Bot {
+	bot_with_client(fake, guild, fake.client()).await
+}
+
+/// Like [`bot`], but with another client for the API.
+pub async fn bot_with_client(fake: &FakeDiscord, guild: &str, client: Arc<Client>) -> Bot {
+	let config = format!("[[guilds]]\nid = \"{}\"\n{}", GUILD, guild.replace("{ebas}", &fake.ebas_url()));
+	let config = config::from_str(&config).expect("Test configuration is invalid.");
+
+	let secrets = secrets::Secrets {
+		discord: secrets::Discord {
+			token: String::from("test"),
+			application: twilight_model::id::Id::new(APPLICATION),
+		},
+		ebas: Some(secrets::Ebas {
+			api_key: String::from("key"),
+			id: String::from("1"),
+		}),
+		associations: Default::default(),
+	};
+
+	let migration = state::MigrationContext::new(vec![twilight_model::id::Id::new(GUILD)]);
+	let state = state::sqlite::SqliteStorage::open(":memory:", &migration).expect("Couldn't open state.");
+
+	Bot::builder()
+		.config(config)
+		.secrets(secrets)
+		.state(Box::new(state))
+		.client(client)
+		.logging(false)
+		.build()
+		.expect("Couldn't set up the bot.")
+}
+
+/// Delivers an interaction to the bot as if it came from the gateway.
+pub fn inject(bot: &Bot, interaction: Value) -> tokio::task::JoinHandle<()> {
+	let interaction: Interaction = serde_json::from_value(interaction).expect("Test interaction is invalid.");
+	let event = Event::InteractionCreate(Box::new(InteractionCreate(interaction)));
+	tokio::spawn(bot.handle_event(event))
+}
+
+pub fn user(id: u64) -> Value {
+	json!({
+		"id": id.to_string(),
+		"username": format!("user{}", id),
+		"discriminator": "0",
+		"avatar": null,
+	})
+}
+
+pub fn member(user_id: u64, roles: &[u64]) -> Value {
+	json!({
+		"user": user(user_id),
+		"roles": roles.iter().map(|r| r.to_string()).collect::<Vec<_>>(),
+		"joined_at": "2024-01-01T00:00:00.000000+00:00",
+		"deaf": false,
+		"mute": false,
+		"flags": 0,
+	})
+}
+
+/// A slash command `/<command> <subcommand>` with string options, sent by `user`.
+pub fn command(id: u64, user: u64, roles: &[u64], command: &str, subcommand: &str, options: &[(&str, &str)]) -> Value {
+	let options: Vec<Value> = options.iter()
+		.map(|(name, value)| json!({ "name": name, "type": 3, "value": value }))
+		.collect();
+
+	json!({
+		"id": id.to_string(),
+		"application_id": APPLICATION.to_string(),
+		"type": 2,
+		"token": format!("token{}", id),
+		"version": 1,
+		"guild_id": GUILD.to_string(),
+		"channel_id": WELCOME_CHANNEL.to_string(),
+		"member": member(user, roles),
+		"locale": "en-US",
+		"data": {
+			"id": "1",
+			"name": command,
+			"type": 1,
+			"options": [{ "name": subcommand, "type": 1, "options": options }],
+		},
+	})
+}
+
+/// A click on a button with `custom_id`, sent by `user`.
+pub fn button(id: u64, user: u64, roles: &[u64], custom_id: &str) -> Value {
+	json!({
+		"id": id.to_string(),
+		"application_id": APPLICATION.to_string(),
+		"type": 3,
+		"token": format!("token{}", id),
+		"version": 1,
+		"guild_id": GUILD.to_string(),
+		"channel_id": WELCOME_CHANNEL.to_string(),
+		"member": member(user, roles),
+		"locale": "en-US",
+		"message": message(1, WELCOME_CHANNEL, ""),
+		"data": {
+			"custom_id": custom_id,
+			"component_type": 2,
+		},
+	})
+}
+
+/// A message as the API returns it, posted by the bot.
+pub fn message(id: u64, channel: u64, content: &str) -> Value {
+	json!({
+		"id": id.to_string(),
+		"channel_id": channel.to_string(),
+		"author": user(BOT),
+		"content": content,
+		"timestamp": "2024-01-01T00:00:00.000000+00:00",
+		"edited_timestamp": null,
+		"tts": false,
+		"mention_everyone": false,
+		"mentions": [],
+		"mention_roles": [],
+		"attachments": [],
+		"embeds": [],
+		"components": [],
+		"pinned": false,
+		"type": 0,
+	})
+}
+
+/// The error Discord returns for a message that doesn't exist.
+pub fn unknown_message() -> Value {
+	json!({ "code": 10008, "message": "Unknown Message" })
+}
+
+/// Waits until a running command is waiting for a button to be clicked, since a click
+/// that arrives before that is ignored.
+pub async fn wait_for_waiter(bot: &Bot) {
+	for _ in 0..500 {
+		if !bot.framework().waiters.lock().is_empty() {
+			return;
+		}
+		tokio::time::sleep(Duration::from_millis(10)).await;
+	}
+	panic!("Timed out waiting for the command to wait for a button.");
+}
+
+/// The error Discord returns when the bot lacks a permission.
+pub fn missing_permissions() -> Value {
+	json!({ "code": 50013, "message": "Missing Permissions" })
+}
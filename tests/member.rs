@@ -0,0 +1,140 @@
+#![cfg(feature = "member")]
+
+use axum::http::{Method, StatusCode};
+
+use serde_json::json;
+
+mod harness;
+
+use harness::*;
+
+const CONFIG: &str = r#"
+[guilds.ebas]
+url = "{ebas}"
+
+[guilds.member]
+role = "400"
+permission = { purge = [{ role = "401" }] }
+"#;
+
+fn ebas_answer(fake: &FakeDiscord, member_found: bool) {
+	fake.respond(Method::POST, "/ebas/confirm_membership.json", StatusCode::OK, json!({
+		"response": {
+			"request_result": { "error": null },
+			"member_found": member_found,
+		},
+	}));
+}
+
+#[tokio::test]
+async fn verify_adds_member_role() {
+	let fake = FakeDiscord::start().await;
+	let bot = bot(&fake, CONFIG).await;
+	ebas_answer(&fake, true);
+
+	inject(&bot, command(1, 10, &[], "member", "verify", &[("email", "medlem@example.com")])).await.unwrap();
+
+	let ebas = fake.requests_to(Method::POST, "/ebas/confirm_membership.json");
+	assert_eq!(ebas.len(), 1);
+	assert_eq!(ebas[0].json()["request"]["email"], "medlem@example.com");
+
+	assert_eq!(fake.requests_to(Method::PUT, "/guilds/100/members/10/roles/400").len(), 1);
+
+	let response = fake.requests_to(Method::POST, "/interactions/1/token1/callback");
+	assert_eq!(response.len(), 1);
+	let data = &response[0].json()["data"];
+	assert!(data["content"].as_str().unwrap().contains("<@&400>"));
+	assert_eq!(data["flags"], 64);
+}
+
+#[tokio::test]
+async fn verify_rejects_unknown_email() {
+	let fake = FakeDiscord::start().await;
+	let bot = bot(&fake, CONFIG).await;
+	ebas_answer(&fake, false);
+
+	inject(&bot, command(1, 10, &[], "member", "verify", &[("email", "okand@example.com")])).await.unwrap();
+
+	assert!(fake.requests().iter().all(|r| r.method != Method::PUT));
+
+	let response = fake.requests_to(Method::POST, "/interactions/1/token1/callback");
+	assert_eq!(response.len(), 1);
+	assert!(!response[0].json()["data"]["content"].as_str().unwrap().contains("<@&400>"));
+}
+
+#[tokio::test]
+async fn verify_reports_ebas_errors() {
+	let fake = FakeDiscord::start().await;
+	let bot = bot(&fake, CONFIG).await;
+	fake.respond(Method::POST, "/ebas/confirm_membership.json", StatusCode::INTERNAL_SERVER_ERROR, json!({}));
+
+	inject(&bot, command(1, 10, &[], "member", "verify", &[("email", "medlem@example.com")])).await.unwrap();
+
+	assert!(fake.requests().iter().all(|r| r.method != Method::PUT));
+
+	let response = fake.requests_to(Method::POST, "/interactions/1/token1/callback");
+	assert_eq!(response.len(), 1);
+	assert_eq!(response[0].json()["data"]["content"], "Something went wrong.");
+}
+
+#[tokio::test]
+async fn purge_removes_member_role_after_confirmation() {
+	let fake = FakeDiscord::start().await;
+	let bot = bot(&fake, CONFIG).await;
+	fake.respond(Method::GET, "/guilds/100/members", StatusCode::OK, json!([
+		member(10, &[MEMBER_ROLE]),
+		member(11, &[]),
+		member(12, &[MEMBER_ROLE, ADMIN_ROLE]),
+	]));
+	fake.respond(Method::POST, "/webhooks/200/token1", StatusCode::OK, message(900, WELCOME_CHANNEL, ""));
+
+	let purge = inject(&bot, command(1, 20, &[ADMIN_ROLE], "member", "purge", &[]));
+
+	fake.wait_for(Method::POST, "/interactions/1/token1/callback", 1).await;
+	wait_for_waiter(&bot).await;
+	inject(&bot, button(2, 20, &[ADMIN_ROLE], "1:member_purge_confirm")).await.unwrap();
+
+	// The number of members is shown in a followup that has to be confirmed again.
+	let followups = fake.wait_for(Method::POST, "/webhooks/200/token1", 1).await;
+	assert_eq!(followups[0].json()["content"], "Found 2 members in <@&400>. Do you want me to remove them from <@&400>?");
+	wait_for_waiter(&bot).await;
+	inject(&bot, button(3, 20, &[ADMIN_ROLE], "1:member_purge_confirm")).await.unwrap();
+
+	purge.await.unwrap();
+
+	assert_eq!(fake.requests_to(Method::DELETE, "/guilds/100/members/10/roles/400").len(), 1);
+	assert_eq!(fake.requests_to(Method::DELETE, "/guilds/100/members/12/roles/400").len(), 1);
+	assert!(fake.requests_to(Method::DELETE, "/guilds/100/members/11/roles/400").is_empty());
+	assert_eq!(fake.requests_to(Method::POST, "/webhooks/200/token1").len(), 2);
+}
+
+#[tokio::test]
+async fn purge_can_be_cancelled() {
+	let fake = FakeDiscord::start().await;
+	let bot = bot(&fake, CONFIG).await;
+
+	let purge = inject(&bot, command(1, 20, &[ADMIN_ROLE], "member", "purge", &[]));
+
+	fake.wait_for(Method::POST, "/interactions/1/token1/callback", 1).await;
+	wait_for_waiter(&bot).await;
+	inject(&bot, button(2, 20, &[ADMIN_ROLE], "1:member_purge_cancel")).await.unwrap();
+
+	purge.await.unwrap();
+
+	assert!(fake.requests_to(Method::GET, "/guilds/100/members").is_empty());
+	assert_eq!(fake.requests_to(Method::PATCH, "/webhooks/200/token1/messages/@original").len(), 1);
+}
+
+#[tokio::test]
+async fn purge_requires_permission() {
+	let fake = FakeDiscord::start().await;
+	let bot = bot(&fake, CONFIG).await;
+
+	inject(&bot, command(1, 20, &[MEMBER_ROLE], "member", "purge", &[])).await.unwrap();
+
+	let response = fake.requests_to(Method::POST, "/interactions/1/token1/callback");
+	assert_eq!(response.len(), 1);
+	assert_eq!(response[0].json()["data"]["flags"], 64);
+	assert!(bot.framework().waiters.lock().is_empty());
+	assert!(fake.requests_to(Method::GET, "/guilds/100/members").is_empty());
+}
@@ -0,0 +1,55 @@
+use axum::http::{Method, StatusCode};
+
+use serde_json::json;
+
+mod harness;
+
+use harness::*;
+
+const CONFIG: &str = r#"
+[guilds.welcome]
+channel = "500"
+text = "Regler"
+
+[guilds.welcome.rules]
+role = "402"
+permission = { reset = [{ role = "401" }] }
+
+[guilds.ebas]
+url = "{ebas}"
+
+[guilds.member]
+role = "400"
+permission = { purge = [] }
+"#;
+
+#[tokio::test]
+async fn reset_removes_role_from_everyone_with_it() {
+	let fake = FakeDiscord::start().await;
+	let bot = bot(&fake, CONFIG).await;
+	// Member 10 was given the role by hand, so only the guild knows that it has it.
+	fake.respond(Method::GET, "/guilds/100/members", StatusCode::OK, json!([
+		member(10, &[402]),
+		member(11, &[]),
+	]));
+
+	inject(&bot, command(1, 20, &[ADMIN_ROLE], "rules", "reset", &[])).await.unwrap();
+
+	assert_eq!(fake.requests_to(Method::DELETE, "/guilds/100/members/10/roles/402").len(), 1);
+	assert!(fake.requests_to(Method::DELETE, "/guilds/100/members/11/roles/402").is_empty());
+	let response = fake.requests_to(Method::PATCH, "/webhooks/200/token1/messages/@original");
+	assert_eq!(response[0].json()["content"], "1 members have to accept the rules again.");
+}
+
+#[tokio::test]
+async fn reset_reports_unlisted_members() {
+	let fake = FakeDiscord::start().await;
+	let bot = bot(&fake, CONFIG).await;
+	fake.respond(Method::GET, "/guilds/100/members", StatusCode::FORBIDDEN, missing_permissions());
+
+	inject(&bot, command(1, 20, &[ADMIN_ROLE], "rules", "reset", &[])).await.unwrap();
+
+	assert!(fake.requests().iter().all(|r| r.method != Method::DELETE));
+	let response = fake.requests_to(Method::PATCH, "/webhooks/200/token1/messages/@original");
+	assert_eq!(response[0].json()["content"], "I couldn't list the members of the server, so no roles were removed.");
+}
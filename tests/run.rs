@@ -0,0 +1,41 @@
+use axum::http::{Method, StatusCode};
+
+use serde_json::json;
+
+mod harness;
+
+use harness::*;
+use kodbot::RunError;
+
+const CONFIG: &str = r#"
+[guilds.ebas]
+url = "{ebas}"
+
+[guilds.member]
+role = "400"
+permission = { purge = [] }
+"#;
+
+#[tokio::test]
+async fn stops_with_invalid_token() {
+	let fake = FakeDiscord::start().await;
+	let bot = bot(&fake, CONFIG).await;
+	fake.respond(Method::GET, "/users/@me", StatusCode::UNAUTHORIZED, json!({ "code": 0, "message": "401: Unauthorized" }));
+
+	let result = bot.run().await;
+
+	assert!(matches!(result, Err(RunError::InvalidToken)));
+	// Nothing else is tried with a token that doesn't work.
+	assert_eq!(fake.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn stops_when_commands_cant_be_registered() {
+	let fake = FakeDiscord::start().await;
+	let bot = bot(&fake, CONFIG).await;
+	fake.respond(Method::PUT, "/applications/200/guilds/100/commands", StatusCode::FORBIDDEN, missing_permissions());
+
+	let result = bot.run().await;
+
+	assert!(matches!(result, Err(RunError::Commands(guild, _)) if guild.get() == GUILD));
+}
@@ -0,0 +1,190 @@
+use axum::http::{Method, StatusCode};
+
+use twilight_model::id::Id;
+
+mod harness;
+
+use harness::*;
+use kodbot::Bot;
+use kodbot::state::Posted;
+use kodbot::posted;
+use kodbot::welcome;
+
+const CONFIG: &str = r#"
+[guilds.welcome]
+channel = "500"
+text = """
+Hej
+---
+Välkommen"""
+
+[guilds.ebas]
+url = "{ebas}"
+
+[guilds.member]
+role = "400"
+permission = { purge = [] }
+"#;
+
+/// The ids of the welcome messages in the state.
+fn posted(bot: &Bot) -> Vec<u64> {
+	bot.context().state().welcome(Id::new(GUILD)).unwrap()
+		.map(|w| w.messages().iter().map(|m| m.get()).collect())
+		.unwrap_or_default()
+}
+
+#[tokio::test]
+async fn posts_welcome_message() {
+	let fake = FakeDiscord::start().await;
+	let bot = bot(&fake, CONFIG).await;
+	fake.respond_once(Method::POST, "/channels/500/messages", StatusCode::OK, message(600, WELCOME_CHANNEL, "Hej"));
+	fake.respond_once(Method::POST, "/channels/500/messages", StatusCode::OK, message(601, WELCOME_CHANNEL, "Välkommen"));
+
+	let guild = bot.context().config().guild(Id::new(GUILD)).unwrap();
+	let changes = welcome::sync(bot.client(), bot.context(), &guild, false).await.unwrap();
+
+	assert_eq!((changes.posted(), changes.edited(), changes.deleted()), (2, 0, 0));
+	let posts = fake.requests_to(Method::POST, "/channels/500/messages");
+	assert_eq!(posts[0].json()["content"], "Hej");
+	assert_eq!(posts[1].json()["content"], "Välkommen");
+	assert_eq!(posted(&bot), vec![600, 601]);
+}
+
+#[tokio::test]
+async fn keeps_and_edits_posted_messages() {
+	let fake = FakeDiscord::start().await;
+	let bot = bot(&fake, CONFIG).await;
+	bot.context().state().set_welcome(Id::new(GUILD), Posted::new(vec![Id::new(600), Id::new(601)])).unwrap();
+	fake.respond(Method::GET, "/channels/500/messages/600", StatusCode::OK, message(600, WELCOME_CHANNEL, "Hej"));
+	fake.respond(Method::GET, "/channels/500/messages/601", StatusCode::OK, message(601, WELCOME_CHANNEL, "Gammal"));
+	fake.respond(Method::PATCH, "/channels/500/messages/601", StatusCode::OK, message(601, WELCOME_CHANNEL, "Välkommen"));
+
+	let guild = bot.context().config().guild(Id::new(GUILD)).unwrap();
+	let changes = welcome::sync(bot.client(), bot.context(), &guild, false).await.unwrap();
+
+	assert_eq!((changes.unchanged(), changes.edited(), changes.posted()), (1, 1, 0));
+	assert!(fake.requests_to(Method::PATCH, "/channels/500/messages/600").is_empty());
+	assert_eq!(fake.requests_to(Method::PATCH, "/channels/500/messages/601")[0].json()["content"], "Välkommen");
+	assert!(fake.requests_to(Method::POST, "/channels/500/messages").is_empty());
+	assert_eq!(posted(&bot), vec![600, 601]);
+}
+
+#[tokio::test]
+async fn reposts_from_missing_message() {
+	let fake = FakeDiscord::start().await;
+	let bot = bot(&fake, CONFIG).await;
+	bot.context().state().set_welcome(Id::new(GUILD), Posted::new(vec![Id::new(600), Id::new(601)])).unwrap();
+	fake.respond(Method::GET, "/channels/500/messages/600", StatusCode::NOT_FOUND, unknown_message());
+	fake.respond_once(Method::POST, "/channels/500/messages", StatusCode::OK, message(602, WELCOME_CHANNEL, "Hej"));
+	fake.respond_once(Method::POST, "/channels/500/messages", StatusCode::OK, message(603, WELCOME_CHANNEL, "Välkommen"));
+
+	let guild = bot.context().config().guild(Id::new(GUILD)).unwrap();
+	let changes = welcome::sync(bot.client(), bot.context(), &guild, false).await.unwrap();
+
+	// New messages can only be added at the end, so the one after the missing message goes too.
+	assert_eq!((changes.deleted(), changes.posted()), (1, 2));
+	assert!(fake.requests_to(Method::GET, "/channels/500/messages/601").is_empty());
+	assert_eq!(fake.requests_to(Method::DELETE, "/channels/500/messages/601").len(), 1);
+	assert_eq!(posted(&bot), vec![602, 603]);
+}
+
+#[tokio::test]
+async fn keeps_messages_that_cant_be_fetched() {
+	let fake = FakeDiscord::start().await;
+	let bot = bot(&fake, CONFIG).await;
+	bot.context().state().set_welcome(Id::new(GUILD), Posted::new(vec![Id::new(600), Id::new(601)])).unwrap();
+	fake.respond(Method::GET, "/channels/500/messages/600", StatusCode::FORBIDDEN, missing_permissions());
+
+	let guild = bot.context().config().guild(Id::new(GUILD)).unwrap();
+	assert!(welcome::sync(bot.client(), bot.context(), &guild, false).await.is_err());
+
+	// Only a message that is gone is reposted, anything else would leave duplicates behind.
+	assert!(fake.requests().iter().all(|r| r.method == Method::GET));
+	assert_eq!(posted(&bot), vec![600, 601]);
+}
+
+#[tokio::test]
+async fn repost_deletes_and_posts_again() {
+	let fake = FakeDiscord::start().await;
+	let bot = bot(&fake, CONFIG).await;
+	bot.context().state().set_welcome(Id::new(GUILD), Posted::new(vec![Id::new(600), Id::new(601)])).unwrap();
+	fake.respond_once(Method::POST, "/channels/500/messages", StatusCode::OK, message(602, WELCOME_CHANNEL, "Hej"));
+	fake.respond_once(Method::POST, "/channels/500/messages", StatusCode::OK, message(603, WELCOME_CHANNEL, "Välkommen"));
+
+	let guild = bot.context().config().guild(Id::new(GUILD)).unwrap();
+	let changes = welcome::sync(bot.client(), bot.context(), &guild, true).await.unwrap();
+
+	assert_eq!((changes.deleted(), changes.posted()), (2, 2));
+	assert!(fake.requests().iter().all(|r| r.method != Method::GET));
+	assert_eq!(fake.requests_to(Method::DELETE, "/channels/500/messages/600").len(), 1);
+	assert_eq!(fake.requests_to(Method::DELETE, "/channels/500/messages/601").len(), 1);
+	assert_eq!(posted(&bot), vec![602, 603]);
+}
+
+#[tokio::test]
+async fn translation_with_unreadable_image_is_unavailable() {
+	let dir = std::env::temp_dir().join(format!("kodbot-translation-{}", std::process::id()));
+	std::fs::create_dir_all(&dir).unwrap();
+	let image = dir.join("bild.png");
+	std::fs::write(&image, b"png").unwrap();
+	let embeds = dir.join("embeds.toml");
+	std::fs::write(&embeds, format!("[[embeds]]\ntitle = \"Welcome\"\nimage = {:?}\n", image.display().to_string())).unwrap();
+
+	let config = format!(r#"
+[guilds.welcome]
+channel = "500"
+text = "Välkommen"
+
+[guilds.welcome.translations.en-US]
+label = "English"
+text = "Welcome"
+embeds = {:?}
+
+[guilds.ebas]
+url = "{{ebas}}"
+
+[guilds.member]
+role = "400"
+permission = {{ purge = [] }}
+"#, embeds.display().to_string());
+	let fake = FakeDiscord::start().await;
+	let bot = bot(&fake, &config).await;
+	// The image can't be read anymore, although it still exists.
+	std::fs::remove_file(&image).unwrap();
+	std::fs::create_dir(&image).unwrap();
+
+	inject(&bot, button(1, 10, &[], "welcome:en-US")).await.unwrap();
+	std::fs::remove_dir_all(&dir).unwrap();
+
+	let response = fake.requests_to(Method::POST, "/interactions/1/token1/callback");
+	assert_eq!(response[0].json()["data"]["content"], "This translation isn't available right now.");
+}
+
+#[tokio::test]
+async fn repost_keeps_old_messages_when_posting_fails() {
+	let fake = FakeDiscord::start().await;
+	let bot = bot(&fake, CONFIG).await;
+	bot.context().state().set_welcome(Id::new(GUILD), Posted::new(vec![Id::new(600), Id::new(601)])).unwrap();
+	fake.respond(Method::POST, "/channels/500/messages", StatusCode::FORBIDDEN, missing_permissions());
+
+	let guild = bot.context().config().guild(Id::new(GUILD)).unwrap();
+	let result = welcome::sync(bot.client(), bot.context(), &guild, true).await;
+
+	assert!(matches!(result, Err(posted::SyncError::Http(_))));
+	assert!(fake.requests().iter().all(|r| r.method != Method::DELETE));
+	assert_eq!(posted(&bot), vec![600, 601]);
+}
+
+#[tokio::test]
+async fn sync_command_reports_discord_errors() {
+	let config = CONFIG.replace("[guilds.ebas]", "[guilds.welcome.permission]\nmanage = [{ role = \"401\" }]\n\n[guilds.ebas]");
+	let fake = FakeDiscord::start().await;
+	let bot = bot(&fake, &config).await;
+	fake.respond(Method::POST, "/channels/500/messages", StatusCode::FORBIDDEN, missing_permissions());
+
+	inject(&bot, command(1, 20, &[ADMIN_ROLE], "welcome", "sync", &[])).await.unwrap();
+
+	let response = fake.requests_to(Method::PATCH, "/webhooks/200/token1/messages/@original");
+	assert!(response[0].json()["content"].as_str().unwrap().contains("Missing Permissions"));
+	assert!(posted(&bot).is_empty());
+}